@@ -23,17 +23,57 @@ Register-ArgumentCompleter -Native -CommandName 'gret' -ScriptBlock {
         'gret' {
             [CompletionResult]::new('-e', 'e', [CompletionResultType]::ParameterName, 'specify the regex expression')
             [CompletionResult]::new('--expr', 'expr', [CompletionResultType]::ParameterName, 'specify the regex expression')
+            [CompletionResult]::new('--file', 'file', [CompletionResultType]::ParameterName, 'read patterns from this file, one per line, blank lines and lines starting with # are skipped, use - for stdin')
             [CompletionResult]::new('-t', 't', [CompletionResultType]::ParameterName, 'specify the search target. If none provided, search the current directory.')
             [CompletionResult]::new('--target', 'target', [CompletionResultType]::ParameterName, 'specify the search target. If none provided, search the current directory.')
             [CompletionResult]::new('--max_depth', 'max_depth', [CompletionResultType]::ParameterName, 'the max depth the searcher will search')
+            [CompletionResult]::new('--max-filesize', 'max-filesize', [CompletionResultType]::ParameterName, 'skip files larger than this size, a number of bytes with an optional K, M or G suffix')
+            [CompletionResult]::new('--max-count', 'max-count', [CompletionResultType]::ParameterName, 'stop searching a file after this many of its lines matched')
+            [CompletionResult]::new('--max-total', 'max-total', [CompletionResultType]::ParameterName, 'stop the search after this many lines matched across all files')
+            [CompletionResult]::new('--sort', 'sort', [CompletionResultType]::ParameterName, 'order the entries of each directory from smallest to largest by path, modified, accessed, created, size or matches')
+            [CompletionResult]::new('--sortr', 'sortr', [CompletionResultType]::ParameterName, 'like --sort but from largest to smallest')
+            [CompletionResult]::new('-r', 'r', [CompletionResultType]::ParameterName, 'show each match replaced by this, $1, $name and ${name} are replaced by the groups of the match, files are not changed')
+            [CompletionResult]::new('--replace', 'replace', [CompletionResultType]::ParameterName, 'show each match replaced by this, $1, $name and ${name} are replaced by the groups of the match, files are not changed')
+            [CompletionResult]::new('--backup-suffix', 'backup-suffix', [CompletionResultType]::ParameterName, 'before writing a file copy it to its path with this added to the end, a file whose backup is already there is not written')
+            [CompletionResult]::new('--ignore-file', 'ignore-file', [CompletionResultType]::ParameterName, 'also ignore the paths in this file, written like a .gitignore, can be given more than once')
+            [CompletionResult]::new('--explain', 'explain', [CompletionResultType]::ParameterName, 'instead of searching, tell whether this path is searched and if not why it is skipped')
+            [CompletionResult]::new('--binary', 'binary', [CompletionResultType]::ParameterName, 'what to do with binary files, skip them, report how many matches they have or search them as text with the unprintable bytes escaped')
+            [CompletionResult]::new('--pre', 'pre', [CompletionResultType]::ParameterName, 'search what this command writes for each file instead of the file, it is given the path of the file and the file on stdin')
+            [CompletionResult]::new('--pre-glob', 'pre-glob', [CompletionResultType]::ParameterName, 'only run --pre on files that match this glob, start it with ! to leave files out, can be given more than once')
+            [CompletionResult]::new('-E', 'E ', [CompletionResultType]::ParameterName, 'decode files without a byte order mark from this encoding, any WHATWG label like utf-16le, shift_jis or latin1, files with one are always decoded')
+            [CompletionResult]::new('--encoding', 'encoding', [CompletionResultType]::ParameterName, 'decode files without a byte order mark from this encoding, any WHATWG label like utf-16le, shift_jis or latin1, files with one are always decoded')
+            [CompletionResult]::new('--max-columns', 'max-columns', [CompletionResultType]::ParameterName, 'lines longer than this many bytes are replaced by a note of how many matches they had')
             [CompletionResult]::new('-b', 'b', [CompletionResultType]::ParameterName, 'if this option is present there will be no styling of text')
             [CompletionResult]::new('--bland', 'bland', [CompletionResultType]::ParameterName, 'if this option is present there will be no styling of text')
             [CompletionResult]::new('-c', 'c', [CompletionResultType]::ParameterName, 'if this option is present, display number of files matched in a directory and number of lines matched in a file')
             [CompletionResult]::new('--show_count', 'show_count', [CompletionResultType]::ParameterName, 'if this option is present, display number of files matched in a directory and number of lines matched in a file')
-            [CompletionResult]::new('-a', 'a', [CompletionResultType]::ParameterName, 'if this option is present gret will search hidden files')
-            [CompletionResult]::new('--hidden', 'hidden', [CompletionResultType]::ParameterName, 'if this option is present gret will search hidden files')
+            [CompletionResult]::new('-a', 'a', [CompletionResultType]::ParameterName, 'if this option is present gret will search hidden files, the .git, .hg and .svn directories are still skipped')
+            [CompletionResult]::new('--hidden', 'hidden', [CompletionResultType]::ParameterName, 'if this option is present gret will search hidden files, the .git, .hg and .svn directories are still skipped')
+            [CompletionResult]::new('--all', 'all', [CompletionResultType]::ParameterName, 'if this option is present search everything, hidden and ignored files, the .git, .hg and .svn directories and binary files')
+            [CompletionResult]::new('-u', 'u', [CompletionResultType]::ParameterName, '-u is --no-ignore, -uu also searches hidden files and -uuu is --all')
+            [CompletionResult]::new('-P', 'P ', [CompletionResultType]::ParameterName, 'if this option is present match with PCRE2, which supports look around and back references')
+            [CompletionResult]::new('--pcre2', 'pcre2', [CompletionResultType]::ParameterName, 'if this option is present match with PCRE2, which supports look around and back references')
             [CompletionResult]::new('-l', 'l', [CompletionResultType]::ParameterName, 'if this option is present show the line number of the matched text')
             [CompletionResult]::new('--line_number', 'line_number', [CompletionResultType]::ParameterName, 'if this option is present show the line number of the matched text')
+            [CompletionResult]::new('--all-match', 'all-match', [CompletionResultType]::ParameterName, 'if this option is present only show files where every pattern matches somewhere')
+            [CompletionResult]::new('--and', 'and', [CompletionResultType]::ParameterName, 'if this option is present only show lines where every pattern matches')
+            [CompletionResult]::new('-p', 'p', [CompletionResultType]::ParameterName, 'if this option is present match the patterns against the path of each file and directory instead of the contents of files')
+            [CompletionResult]::new('--path-match', 'path-match', [CompletionResultType]::ParameterName, 'if this option is present match the patterns against the path of each file and directory instead of the contents of files')
+            [CompletionResult]::new('--write', 'write', [CompletionResultType]::ParameterName, 'if this option is present write the replacements to the matched files and show the files and lines that changed')
+            [CompletionResult]::new('--no-ignore', 'no-ignore', [CompletionResultType]::ParameterName, 'if this option is present don''t use .gitignore, .ignore, .gretignore or the global ignore files, files given with --ignore-file are still used')
+            [CompletionResult]::new('--no-ignore-vcs', 'no-ignore-vcs', [CompletionResultType]::ParameterName, 'if this option is present don''t use .gitignore, .git/info/exclude or the global git ignore')
+            [CompletionResult]::new('--no-ignore-parent', 'no-ignore-parent', [CompletionResultType]::ParameterName, 'if this option is present don''t use ignore files in the directories above the target')
+            [CompletionResult]::new('--no-ignore-global', 'no-ignore-global', [CompletionResultType]::ParameterName, 'if this option is present don''t use the global git ignore file')
+            [CompletionResult]::new('--debug', 'debug', [CompletionResultType]::ParameterName, 'if this option is present show every entry that was skipped and why after the results')
+            [CompletionResult]::new('-L', 'L ', [CompletionResultType]::ParameterName, 'if this option is present walk into linked directories, links back to a directory they are inside of are skipped')
+            [CompletionResult]::new('--follow', 'follow', [CompletionResultType]::ParameterName, 'if this option is present walk into linked directories, links back to a directory they are inside of are skipped')
+            [CompletionResult]::new('-z', 'z', [CompletionResultType]::ParameterName, 'if this option is present search inside files compressed with gzip, bzip2, xz, zstd or lz4, running that program on them for all but gzip')
+            [CompletionResult]::new('--search-zip', 'search-zip', [CompletionResultType]::ParameterName, 'if this option is present search inside files compressed with gzip, bzip2, xz, zstd or lz4, running that program on them for all but gzip')
+            [CompletionResult]::new('--archives', 'archives', [CompletionResultType]::ParameterName, 'if this option is present search .zip, .jar, .war, .ear, .tar, .tar.gz and .tgz files as directories of the files inside of them')
+            [CompletionResult]::new('--crlf', 'crlf', [CompletionResultType]::ParameterName, 'if this option is present treat every file as having \r\n line endings, otherwise it is detected from the first line of each file')
+            [CompletionResult]::new('--trim', 'trim', [CompletionResultType]::ParameterName, 'if this option is present strip leading and trailing whitespace from the shown lines')
+            [CompletionResult]::new('--max-columns-preview', 'max-columns-preview', [CompletionResultType]::ParameterName, 'if this option is present show a window of --max-columns bytes around each match of a long line instead of leaving it out, unless the windows come to more than four times --max-columns')
+            [CompletionResult]::new('--vimgrep', 'vimgrep', [CompletionResultType]::ParameterName, 'if this option is present print each match as path:line:column:text instead of a tree')
             [CompletionResult]::new('-m', 'm', [CompletionResultType]::ParameterName, 'if this arg is present gret will show matches in a menu to be selected from')
             [CompletionResult]::new('--menu', 'menu', [CompletionResultType]::ParameterName, 'if this arg is present gret will show matches in a menu to be selected from')
             [CompletionResult]::new('-f', 'f', [CompletionResultType]::ParameterName, 'if this arg is present just print out the file names of matches')
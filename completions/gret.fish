@@ -1,10 +1,44 @@
 complete -c gret -s e -l expr -d 'specify the regex expression' -r
+complete -c gret -l file -d 'read patterns from this file, one per line, blank lines and lines starting with # are skipped, use - for stdin' -r -F
 complete -c gret -s t -l target -d 'specify the search target. If none provided, search the current directory.' -r -F
 complete -c gret -l max_depth -d 'the max depth the searcher will search' -r
+complete -c gret -l max-filesize -d 'skip files larger than this size, a number of bytes with an optional K, M or G suffix' -r
+complete -c gret -l max-count -d 'stop searching a file after this many of its lines matched' -r
+complete -c gret -l max-total -d 'stop the search after this many lines matched across all files' -r
+complete -c gret -l sort -d 'order the entries of each directory from smallest to largest by path, modified, accessed, created, size or matches' -r -f -a "{path	'',modified	'',accessed	'',created	'',size	'',matches	''}"
+complete -c gret -l sortr -d 'like --sort but from largest to smallest' -r -f -a "{path	'',modified	'',accessed	'',created	'',size	'',matches	''}"
+complete -c gret -s r -l replace -d 'show each match replaced by this, $1, $name and ${name} are replaced by the groups of the match, files are not changed' -r
+complete -c gret -l backup-suffix -d 'before writing a file copy it to its path with this added to the end, a file whose backup is already there is not written' -r
+complete -c gret -l ignore-file -d 'also ignore the paths in this file, written like a .gitignore, can be given more than once' -r -F
+complete -c gret -l explain -d 'instead of searching, tell whether this path is searched and if not why it is skipped' -r -F
+complete -c gret -l binary -d 'what to do with binary files, skip them, report how many matches they have or search them as text with the unprintable bytes escaped' -r -f -a "{skip	'',report	'',text	''}"
+complete -c gret -l pre -d 'search what this command writes for each file instead of the file, it is given the path of the file and the file on stdin' -r -f -a "(__fish_complete_command)"
+complete -c gret -l pre-glob -d 'only run --pre on files that match this glob, start it with ! to leave files out, can be given more than once' -r
+complete -c gret -s E -l encoding -d 'decode files without a byte order mark from this encoding, any WHATWG label like utf-16le, shift_jis or latin1, files with one are always decoded' -r
+complete -c gret -l max-columns -d 'lines longer than this many bytes are replaced by a note of how many matches they had' -r
 complete -c gret -s b -l bland -d 'if this option is present there will be no styling of text'
 complete -c gret -s c -l show_count -d 'if this option is present, display number of files matched in a directory and number of lines matched in a file'
-complete -c gret -s a -l hidden -d 'if this option is present gret will search hidden files'
+complete -c gret -s a -l hidden -d 'if this option is present gret will search hidden files, the .git, .hg and .svn directories are still skipped'
+complete -c gret -l all -d 'if this option is present search everything, hidden and ignored files, the .git, .hg and .svn directories and binary files'
+complete -c gret -s u -d '-u is --no-ignore, -uu also searches hidden files and -uuu is --all'
+complete -c gret -s P -l pcre2 -d 'if this option is present match with PCRE2, which supports look around and back references'
 complete -c gret -s l -l line_number -d 'if this option is present show the line number of the matched text'
+complete -c gret -l all-match -d 'if this option is present only show files where every pattern matches somewhere'
+complete -c gret -l and -d 'if this option is present only show lines where every pattern matches'
+complete -c gret -s p -l path-match -d 'if this option is present match the patterns against the path of each file and directory instead of the contents of files'
+complete -c gret -l write -d 'if this option is present write the replacements to the matched files and show the files and lines that changed'
+complete -c gret -l no-ignore -d 'if this option is present don\'t use .gitignore, .ignore, .gretignore or the global ignore files, files given with --ignore-file are still used'
+complete -c gret -l no-ignore-vcs -d 'if this option is present don\'t use .gitignore, .git/info/exclude or the global git ignore'
+complete -c gret -l no-ignore-parent -d 'if this option is present don\'t use ignore files in the directories above the target'
+complete -c gret -l no-ignore-global -d 'if this option is present don\'t use the global git ignore file'
+complete -c gret -l debug -d 'if this option is present show every entry that was skipped and why after the results'
+complete -c gret -s L -l follow -d 'if this option is present walk into linked directories, links back to a directory they are inside of are skipped'
+complete -c gret -s z -l search-zip -d 'if this option is present search inside files compressed with gzip, bzip2, xz, zstd or lz4, running that program on them for all but gzip'
+complete -c gret -l archives -d 'if this option is present search .zip, .jar, .war, .ear, .tar, .tar.gz and .tgz files as directories of the files inside of them'
+complete -c gret -l crlf -d 'if this option is present treat every file as having \\r\\n line endings, otherwise it is detected from the first line of each file'
+complete -c gret -l trim -d 'if this option is present strip leading and trailing whitespace from the shown lines'
+complete -c gret -l max-columns-preview -d 'if this option is present show a window of --max-columns bytes around each match of a long line instead of leaving it out, unless the windows come to more than four times --max-columns'
+complete -c gret -l vimgrep -d 'if this option is present print each match as path:line:column:text instead of a tree'
 complete -c gret -s m -l menu -d 'if this arg is present gret will show matches in a menu to be selected from'
 complete -c gret -s f -l files -d 'if this arg is present just print out the file names of matches'
 complete -c gret -s h -l help -d 'Print help'
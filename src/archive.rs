@@ -0,0 +1,413 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+// zip and tar archives are read as directories, each file in one is given
+// with its path inside the archive and the directories come from those paths
+
+use crate::errors::Errors;
+use flate2::read::MultiGzDecoder;
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use zip::result::ZipError;
+
+// DEFLATE can't make more than this many bytes out of each one
+const MAX_RATIO: u64 = 1032;
+// entries larger than this aren't read even without a max file size
+const MAX_ENTRY_LEN: u64 = 1 << 32;
+
+#[derive(Clone, Copy)]
+pub(crate) enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+/// the kind of archive `path` is from how its name ends
+pub(crate) fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+    let name: String = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+    if [".zip", ".jar", ".war", ".ear"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+    {
+        Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar") {
+        Some(ArchiveKind::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(ArchiveKind::TarGz)
+    } else {
+        None
+    }
+}
+
+/// Where a file that was read from an archive is.
+pub struct ArchiveEntry {
+    pub archive: PathBuf,
+    /// the path of the file inside of the archive, separated by `/`
+    pub name: String,
+}
+
+impl ArchiveEntry {
+    /// Write the file out of the archive to its path inside of the
+    /// archive under `dir`, for programs that need a real file.
+    pub fn extract(&self, dir: &Path) -> Result<PathBuf, Errors> {
+        let io_error = |e: io::Error| Errors::IOError {
+            cause: format!(
+                "failed to extract `{}` from `{}`: {}",
+                self.name,
+                self.archive.display(),
+                e
+            ),
+        };
+        let kind = archive_kind(&self.archive).ok_or_else(|| {
+            io_error(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "it isn't an archive",
+            ))
+        })?;
+        // the name has no `..` in it, so it stays inside of `dir`
+        let path: PathBuf = dir.join(&self.name);
+        let mut written: Option<io::Result<()>> = None;
+        read_archive(
+            &self.archive,
+            kind,
+            &|name, _| name == self.name,
+            &mut |name, contents| {
+                if name != self.name || written.is_some() {
+                    return;
+                }
+                written = match contents {
+                    Contents::Reader(reader) => Some(write_to(&path, reader)),
+                    Contents::Unreadable(e) => Some(Err(e)),
+                    Contents::NotRead => None,
+                };
+            },
+        )
+        .map_err(io_error)?;
+        match written {
+            Some(written) => written.map_err(io_error)?,
+            None => {
+                return Err(io_error(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "it isn't in the archive or is too large to read",
+                )))
+            }
+        }
+        Ok(path)
+    }
+}
+
+fn write_to(path: &Path, reader: &mut dyn Read) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    io::copy(reader, &mut fs::File::create(path)?)?;
+    Ok(())
+}
+
+/// What is given for each file in an archive.
+pub(crate) enum Contents<'a> {
+    /// `read` was false for it or it is too large to be read
+    NotRead,
+    Reader(&'a mut dyn Read),
+    /// it is stored in a way that can't be read, like encrypted
+    Unreadable(io::Error),
+}
+
+/// Call `found` with the path of each file in the archive and its
+/// contents. The contents are only read when `read` is true for the
+/// path and the size of the file and the size could be real for an
+/// archive of its length.
+pub(crate) fn read_archive(
+    path: &Path,
+    kind: ArchiveKind,
+    read: &dyn Fn(&str, u64) -> bool,
+    found: &mut dyn FnMut(String, Contents),
+) -> io::Result<()> {
+    let file = fs::File::open(path)?;
+    let most: u64 = file
+        .metadata()?
+        .len()
+        .saturating_mul(MAX_RATIO)
+        .min(MAX_ENTRY_LEN);
+    let read = |name: &str, size: u64| size <= most && read(name, size);
+    match kind {
+        ArchiveKind::Zip => read_zip(file, &read, found),
+        ArchiveKind::Tar => read_tar(file, &read, found),
+        ArchiveKind::TarGz => read_tar(MultiGzDecoder::new(BufReader::new(file)), &read, found),
+    }
+}
+
+/// the path of an entry without empty and `.` parts and `\` made into `/`,
+/// `None` for an entry that is a directory or would be outside the archive
+fn clean_name(raw: &str) -> Option<String> {
+    if raw.ends_with('/') {
+        return None;
+    }
+    let mut parts: Vec<&str> = Vec::new();
+    for part in raw.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            _ => parts.push(part),
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+/// the entries are found from the central directory at the end of the file
+fn read_zip(
+    file: fs::File,
+    read: &dyn Fn(&str, u64) -> bool,
+    found: &mut dyn FnMut(String, Contents),
+) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+    for i in 0..archive.len() {
+        let (name, size) = {
+            let entry = archive.by_index_raw(i)?;
+            (entry.name().to_string(), entry.size())
+        };
+        let Some(name) = clean_name(&name) else {
+            continue;
+        };
+        if !read(&name, size) {
+            found(name, Contents::NotRead);
+            continue;
+        }
+        match archive.by_index(i) {
+            Ok(entry) => found(name, Contents::Reader(&mut Capped::new(entry, size))),
+            Err(
+                e @ (ZipError::UnsupportedArchive(_) | ZipError::CompressionMethodNotSupported(_)),
+            ) => found(name, Contents::Unreadable(e.into())),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// the entries are read in order, the ones that aren't read are skipped over
+fn read_tar(
+    reader: impl Read,
+    read: &dyn Fn(&str, u64) -> bool,
+    found: &mut dyn FnMut(String, Contents),
+) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let kind = entry.header().entry_type();
+        // directories, links and the like have nothing to search
+        if !kind.is_file() && !kind.is_contiguous() {
+            continue;
+        }
+        let Some(name) = clean_name(&String::from_utf8_lossy(&entry.path_bytes())) else {
+            continue;
+        };
+        let size: u64 = entry.size();
+        if read(&name, size) {
+            found(name, Contents::Reader(&mut Capped::new(&mut entry, size)));
+        } else {
+            found(name, Contents::NotRead);
+        }
+    }
+    Ok(())
+}
+
+/// Gives out at most `left` bytes of an entry and fails if it holds
+/// more, a compressed entry can make far more than its header says.
+struct Capped<R: Read> {
+    reader: R,
+    left: u64,
+}
+
+impl<R: Read> Capped<R> {
+    fn new(reader: R, len: u64) -> Capped<R> {
+        Capped { reader, left: len }
+    }
+}
+
+impl<R: Read> Read for Capped<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.left == 0 {
+            return match self.reader.read(&mut [0])? {
+                0 => Ok(0),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "it holds more than the size in its header",
+                )),
+            };
+        }
+        let len: usize = buf
+            .len()
+            .min(usize::try_from(self.left).unwrap_or(usize::MAX));
+        let n = self.reader.read(&mut buf[..len])?;
+        self.left -= n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use tempfile::TempDir;
+    use zip::write::SimpleFileOptions;
+    use zip::CompressionMethod;
+
+    /// the name and contents of each file `read` is true for, or the error
+    /// reading it
+    fn entries(
+        path: &Path,
+        read: &dyn Fn(&str, u64) -> bool,
+    ) -> io::Result<Vec<(String, io::Result<Vec<u8>>)>> {
+        let mut all = Vec::new();
+        read_archive(
+            path,
+            archive_kind(path).unwrap(),
+            read,
+            &mut |name, contents| {
+                let contents = match contents {
+                    Contents::Reader(reader) => {
+                        let mut buf: Vec<u8> = Vec::new();
+                        reader.read_to_end(&mut buf).map(|_| buf)
+                    }
+                    Contents::Unreadable(e) => Err(e),
+                    Contents::NotRead => return,
+                };
+                all.push((name, contents));
+            },
+        )?;
+        Ok(all)
+    }
+
+    fn all(_: &str, _: u64) -> bool {
+        true
+    }
+
+    fn zip_of(files: &[(&str, &[u8])], method: CompressionMethod) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(method);
+        for (name, contents) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    /// set the size of the only entry in the local and central headers
+    fn set_zip_size(zip: &mut [u8], size: u32) {
+        let central: usize = zip.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        zip[22..26].copy_from_slice(&size.to_le_bytes());
+        zip[central + 24..central + 28].copy_from_slice(&size.to_le_bytes());
+    }
+
+    fn write(dir: &TempDir, name: &str, bytes: &[u8]) -> PathBuf {
+        let path: PathBuf = dir.path().join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn zip_and_tar_entries() {
+        let dir = TempDir::new().unwrap();
+        let files: [(&str, &[u8]); 3] = [
+            ("a.txt", b"hello"),
+            ("./sub\\b.txt", b"there"),
+            ("../out.txt", b"outside"),
+        ];
+        let zip = write(&dir, "a.zip", &zip_of(&files, CompressionMethod::Deflated));
+        let mut tar = tar::Builder::new(Vec::new());
+        for (name, contents) in &files[..2] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            // the builder won't take a name with `..`
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_cksum();
+            tar.append(&header, *contents).unwrap();
+        }
+        let tar = write(&dir, "a.tar", &tar.into_inner().unwrap());
+        for path in [zip, tar] {
+            let found = entries(&path, &all).unwrap();
+            let found: Vec<(&str, &[u8])> = found
+                .iter()
+                .map(|(name, contents)| (name.as_str(), contents.as_ref().unwrap().as_slice()))
+                .collect();
+            assert_eq!(
+                found,
+                [("a.txt", &b"hello"[..]), ("sub/b.txt", &b"there"[..])]
+            );
+        }
+    }
+
+    #[test]
+    fn huge_long_name_in_a_small_tar() {
+        let dir = TempDir::new().unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::GNULongName);
+        header.set_size(i64::MAX as u64);
+        header.set_cksum();
+        let mut bytes: Vec<u8> = header.as_bytes().to_vec();
+        bytes.extend_from_slice(&[b'x'; 1000]);
+        let path = write(&dir, "long.tar", &bytes);
+        assert!(entries(&path, &all).is_err());
+    }
+
+    #[test]
+    fn entries_larger_than_their_header() {
+        let dir = TempDir::new().unwrap();
+        let mut bomb = zip_of(&[("a", &[0; 1 << 20])], CompressionMethod::Deflated);
+        set_zip_size(&mut bomb, 100);
+        let path = write(&dir, "bomb.zip", &bomb);
+        let found = entries(&path, &all).unwrap();
+        let error = found[0].1.as_ref().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn sizes_an_archive_cant_hold_are_not_read() {
+        let dir = TempDir::new().unwrap();
+        let mut zip = zip_of(&[("a", b"hello")], CompressionMethod::Stored);
+        set_zip_size(&mut zip, u32::MAX - 1);
+        let path = write(&dir, "big.zip", &zip);
+        let mut sizes: Vec<u64> = Vec::new();
+        read_archive(&path, ArchiveKind::Zip, &all, &mut |_, contents| {
+            assert!(matches!(contents, Contents::NotRead));
+            sizes.push(0);
+        })
+        .unwrap();
+        assert_eq!(sizes.len(), 1);
+        assert!(entries(&path, &|_, size| size < 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn broken_archives_are_errors() {
+        let dir = TempDir::new().unwrap();
+        let zip = zip_of(&[("a", b"hello")], CompressionMethod::Deflated);
+        for (name, bytes) in [
+            ("cut.zip", &zip[..zip.len() - 10]),
+            ("junk.zip", &b"PK\x05\x06 not a zip"[..]),
+            ("junk.tar.gz", &b"\x1f\x8b not gzip"[..]),
+        ] {
+            let path = write(&dir, name, bytes);
+            assert!(entries(&path, &all).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn extract_writes_under_the_directory() {
+        let dir = TempDir::new().unwrap();
+        let files: [(&str, &[u8]); 2] = [("a.txt", b"one"), ("sub/b.txt", b"two")];
+        let archive = write(&dir, "a.jar", &zip_of(&files, CompressionMethod::Deflated));
+        let out = TempDir::new().unwrap();
+        let entry = ArchiveEntry {
+            archive,
+            name: "sub/b.txt".to_string(),
+        };
+        let path: PathBuf = entry.extract(out.path()).unwrap();
+        assert_eq!(path, out.path().join("sub/b.txt"));
+        assert_eq!(fs::read(path).unwrap(), b"two");
+        let missing = ArchiveEntry {
+            name: "c.txt".to_string(),
+            ..entry
+        };
+        assert!(missing.extract(out.path()).is_err());
+    }
+}
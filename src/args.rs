@@ -1,72 +1,114 @@
 // SPDX-License-Identifier: CC-BY-4.0
 
 use crate::command::generate_command;
-use crate::errors::Errors;
-use crate::formats;
 use atty::Stream;
-use regex::bytes::Regex;
+use clap::ArgMatches;
+use gret::{BinaryMode, Encoding, Errors, SearchOptions, SortBy, TreePrinter};
+use std::fs;
+use std::io::{self, Read};
 use std::path::PathBuf;
 
 pub struct Config {
     pub path: PathBuf,
-    pub patterns: Vec<Regex>,
-    pub max_depth: Option<usize>,
-    pub show_count: bool,
-    pub search_hidden: bool,
-    pub show_line_number: bool,
+    pub search: SearchOptions,
+    pub printer: TreePrinter,
     pub menu: bool,
-    pub is_dir: bool,
-    pub styled: bool,
-    pub just_files: bool,
-    // settings not set by command line arguments
-    pub terminator: String,
-    pub reset: String,
+    pub write: bool,
+    pub backup_suffix: Option<String>,
+    pub explain: Option<PathBuf>,
+    pub debug: bool,
 }
 
 pub fn parse_args() -> Result<Config, Errors> {
     let matches = generate_command().get_matches();
-    let mut patterns: Vec<Regex> = Vec::new();
+    let mut patterns: Vec<String> = Vec::new();
 
     if let Some(expr) = matches.get_one::<String>("expression_pos") {
-        patterns.push(Regex::new(expr).map_err(|_| {
-            return Errors::InvalidRegex {
-                cause: expr.to_string(),
-            };
-        })?);
+        patterns.push(expr.to_string());
     }
     if let Some(exprs) = matches.get_many::<String>("expression") {
-        for e in exprs.into_iter() {
-            patterns.push(Regex::new(e).map_err(|_| {
-                return Errors::InvalidRegex {
-                    cause: e.to_string(),
-                };
-            })?);
+        patterns.extend(exprs.cloned());
+    }
+    if let Some(files) = matches.get_many::<String>("pattern_file") {
+        for file in files {
+            patterns.extend(read_pattern_file(file)?);
         }
     }
 
-    let styled = if *matches.get_one::<bool>("bland").unwrap_or(&false) || !atty::is(Stream::Stdout)
-    {
-        false
-    } else {
-        true
-    };
+    let styled = !*matches.get_one::<bool>("bland").unwrap_or(&false) && atty::is(Stream::Stdout);
 
     let show_count: bool = *matches.get_one::<bool>("show_count").unwrap();
-    let search_hidden: bool = *matches.get_one::<bool>("search_hidden").unwrap();
+    let unrestricted: u8 = *matches.get_one::<u8>("unrestricted").unwrap();
+    let search_hidden: bool =
+        *matches.get_one::<bool>("search_hidden").unwrap() || unrestricted >= 2;
+    let all: bool = *matches.get_one::<bool>("all").unwrap() || unrestricted >= 3;
     let show_line_number: bool = *matches.get_one::<bool>("line_number").unwrap();
     let menu: bool = *matches.get_one::<bool>("menu").unwrap();
     let just_files: bool = *matches.get_one::<bool>("just_files").unwrap();
+    let pcre2: bool = *matches.get_one::<bool>("pcre2").unwrap();
+    let crlf: bool = *matches.get_one::<bool>("crlf").unwrap();
+    let all_match: bool = *matches.get_one::<bool>("all_match").unwrap();
+    let and: bool = *matches.get_one::<bool>("and").unwrap();
+    let path_match: bool = *matches.get_one::<bool>("path_match").unwrap();
+    let trim: bool = *matches.get_one::<bool>("trim").unwrap();
+    let vimgrep: bool = *matches.get_one::<bool>("vimgrep").unwrap();
 
-    let max_depth_str: Option<&String> = matches.get_one::<String>("max_depth");
+    let max_depth: Option<usize> = parse_usize(&matches, "max_depth")?;
 
-    let depth_result: Result<Option<usize>, Errors> = max_depth_str.map_or(Ok(None), |s| {
-        s.parse::<usize>()
-            .map(Some)
-            .map_err(|_| Errors::StringToUsizeFail {
-                cause: s.to_string(),
-            })
-    });
-    let max_depth: Option<usize> = depth_result?;
+    let max_columns: Option<usize> = parse_usize(&matches, "max_columns")?;
+    let max_columns_preview: bool = *matches.get_one::<bool>("max_columns_preview").unwrap();
+
+    let max_count: Option<usize> = parse_usize(&matches, "max_count")?;
+    let max_total: Option<usize> = parse_usize(&matches, "max_total")?;
+
+    let sort_reverse: bool = matches.contains_id("sortr");
+    let sort: Option<SortBy> = matches
+        .get_one::<String>("sort")
+        .or_else(|| matches.get_one::<String>("sortr"))
+        .map(|s| match s.as_str() {
+            "modified" => SortBy::Modified,
+            "accessed" => SortBy::Accessed,
+            "created" => SortBy::Created,
+            "size" => SortBy::Size,
+            "matches" => SortBy::Matches,
+            _ => SortBy::Path,
+        });
+
+    let replacement: Option<String> = matches.get_one::<String>("replace").cloned();
+    let write: bool = *matches.get_one::<bool>("write").unwrap();
+    let backup_suffix: Option<String> = matches.get_one::<String>("backup_suffix").cloned();
+
+    let no_ignore: bool = *matches.get_one::<bool>("no_ignore").unwrap() || unrestricted >= 1;
+    let no_ignore_vcs: bool = *matches.get_one::<bool>("no_ignore_vcs").unwrap();
+    let no_ignore_parent: bool = *matches.get_one::<bool>("no_ignore_parent").unwrap();
+    let no_ignore_global: bool = *matches.get_one::<bool>("no_ignore_global").unwrap();
+    let ignore_files: Vec<PathBuf> = matches
+        .get_many::<String>("ignore_file")
+        .map_or(Vec::new(), |files| files.map(PathBuf::from).collect());
+
+    let explain: Option<PathBuf> = matches.get_one::<String>("explain").map(PathBuf::from);
+    let debug: bool = *matches.get_one::<bool>("debug").unwrap();
+    let follow: bool = *matches.get_one::<bool>("follow").unwrap();
+    let binary: BinaryMode = match matches.get_one::<String>("binary").map(String::as_str) {
+        Some("report") => BinaryMode::Report,
+        Some("text") => BinaryMode::Text,
+        _ => BinaryMode::Skip,
+    };
+    let search_zip: bool = *matches.get_one::<bool>("search_zip").unwrap();
+    let archives: bool = *matches.get_one::<bool>("archives").unwrap();
+    let pre: Option<PathBuf> = matches.get_one::<String>("pre").map(PathBuf::from);
+    let pre_globs: Vec<String> = matches
+        .get_many::<String>("pre_glob")
+        .map_or(Vec::new(), |globs| globs.cloned().collect());
+    let encoding: Option<Encoding> = matches
+        .get_one::<String>("encoding")
+        .map(|s| Encoding::for_label(s).ok_or_else(|| Errors::UnknownEncoding { cause: s.clone() }))
+        .transpose()?;
+
+    let max_filesize: Option<u64> = matches
+        .get_one::<String>("max_filesize")
+        .map(|s| parse_size(s))
+        .transpose()?;
 
     let target: Option<String> = matches
         .get_one::<String>("target_pos")
@@ -83,21 +125,114 @@ pub fn parse_args() -> Result<Config, Errors> {
         std::env::current_dir().map_err(|_| Errors::FailedToGetCWD)?
     };
 
-    let terminator = formats::get_terminator(menu);
-    let reset = formats::get_reset(menu);
+    #[cfg(feature = "pcre2")]
+    let search = if pcre2 {
+        SearchOptions::new_pcre2(patterns)?
+    } else {
+        SearchOptions::new(patterns)?
+    };
+    #[cfg(not(feature = "pcre2"))]
+    let search = if pcre2 {
+        return Err(Errors::Pcre2Unavailable);
+    } else {
+        SearchOptions::new(patterns)?
+    };
+    let search = search
+        .max_depth(max_depth)
+        .hidden(search_hidden)
+        .max_filesize(max_filesize)
+        .max_count(max_count)
+        .max_total(max_total)
+        .crlf(crlf)
+        .all_match(all_match)
+        .and(and)
+        .path_match(path_match)
+        .sort(sort, sort_reverse)
+        .replace(replacement)
+        .no_ignore(no_ignore)
+        .no_ignore_vcs(no_ignore_vcs)
+        .no_ignore_parent(no_ignore_parent)
+        .no_ignore_global(no_ignore_global)
+        .ignore_files(ignore_files)
+        .all(all)
+        .follow(follow)
+        .binary(binary)
+        .encoding(encoding)
+        .search_zip(search_zip)
+        .archives(archives)
+        .pre(pre)
+        .pre_globs(pre_globs)?;
+
+    // after writing, the files and how many of their lines changed are shown
+    let printer = TreePrinter::new()
+        .styled(styled)
+        .show_count(show_count || write)
+        .summary(write)
+        .line_number(show_line_number)
+        .just_files(just_files)
+        .trim(trim)
+        .vimgrep(vimgrep)
+        .max_columns(max_columns)
+        .max_columns_preview(max_columns_preview)
+        .menu(menu);
 
     Ok(Config {
-        is_dir: path.is_dir(),
         path,
-        styled,
-        patterns,
-        show_count,
-        search_hidden,
-        max_depth,
-        show_line_number,
+        search,
+        printer,
         menu,
-        terminator,
-        reset,
-        just_files,
+        write,
+        backup_suffix,
+        explain,
+        debug,
     })
 }
+
+/// the number given to the option `id`, `None` when it isn't given
+fn parse_usize(matches: &ArgMatches, id: &str) -> Result<Option<usize>, Errors> {
+    matches
+        .get_one::<String>(id)
+        .map(|s| {
+            s.parse::<usize>().map_err(|_| Errors::StringToUsizeFail {
+                cause: s.to_string(),
+            })
+        })
+        .transpose()
+}
+
+fn parse_size(s: &str) -> Result<u64, Errors> {
+    let err = || Errors::InvalidFileSize {
+        cause: s.to_string(),
+    };
+    let (num, multiplier) = match s.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&s[..s.len() - 1], 1 << 10),
+        Some('M') => (&s[..s.len() - 1], 1 << 20),
+        Some('G') => (&s[..s.len() - 1], 1 << 30),
+        _ => (s, 1),
+    };
+    num.parse::<u64>()
+        .map_err(|_| err())?
+        .checked_mul(multiplier)
+        .ok_or_else(err)
+}
+
+/// one pattern per line, skipping blank lines and `#` comments
+fn read_pattern_file(file: &str) -> Result<Vec<String>, Errors> {
+    let contents: String = if file == "-" {
+        let mut contents = String::new();
+        io::stdin().read_to_string(&mut contents).map(|_| contents)
+    } else {
+        fs::read_to_string(file)
+    }
+    .map_err(|e| Errors::IOError {
+        cause: format!("failed to read patterns from `{}`: {}", file, e),
+    })?;
+    Ok(contents
+        .lines()
+        .filter(|line| {
+            let line = line.trim_start();
+            !line.is_empty() && !line.starts_with('#')
+        })
+        .map(|line| line.to_string())
+        .collect())
+}
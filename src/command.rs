@@ -9,6 +9,8 @@ line tool that searches a directory or file
 for a matching regex expression and displays
 matches in a tree.
 ";
+const BINARY: [&str; 3] = ["skip", "report", "text"];
+const SORT_BY: [&str; 6] = ["path", "modified", "accessed", "created", "size", "matches"];
 const HELP: &str = "{name}
 by {author}
 {about}
@@ -27,7 +29,7 @@ pub fn generate_command() -> Command {
     for opt in get_options() {
         command = command.arg(opt);
     }
-    return command;
+    command
 }
 
 fn get_options() -> Vec<Arg> {
@@ -48,19 +50,205 @@ fn get_options() -> Vec<Arg> {
             .long("hidden")
             .short('a')
             .value_name("Search Hidden")
-            .help("if this option is present gret will search hidden files")
+            .help("if this option is present gret will search hidden files, the .git, .hg and .svn directories are still skipped")
             .action(ArgAction::SetTrue),
+        Arg::new("all")
+            .long("all")
+            .value_name("All")
+            .help("if this option is present search everything, hidden and ignored files, the .git, .hg and .svn directories and binary files")
+            .action(ArgAction::SetTrue),
+        Arg::new("unrestricted")
+            .short('u')
+            .value_name("Unrestricted")
+            .help("-u is --no-ignore, -uu also searches hidden files and -uuu is --all")
+            .action(ArgAction::Count),
         Arg::new("max_depth")
             .long("max_depth")
             .value_name("Max Depth")
             .help("the max depth the searcher will search")
             .action(ArgAction::Set),
+        Arg::new("pcre2")
+            .long("pcre2")
+            .short('P')
+            .value_name("PCRE2")
+            .help("if this option is present match with PCRE2, which supports look around and back references")
+            .action(ArgAction::SetTrue),
+        Arg::new("max_filesize")
+            .long("max-filesize")
+            .value_name("Max File Size")
+            .help("skip files larger than this size, a number of bytes with an optional K, M or G suffix")
+            .action(ArgAction::Set),
+        Arg::new("max_count")
+            .long("max-count")
+            .value_name("Max Count")
+            .help("stop searching a file after this many of its lines matched")
+            .action(ArgAction::Set),
+        Arg::new("max_total")
+            .long("max-total")
+            .value_name("Max Total")
+            .help("stop the search after this many lines matched across all files")
+            .action(ArgAction::Set),
         Arg::new("line_number")
             .long("line_number")
             .short('l')
             .value_name("Show Line Number")
             .help("if this option is present show the line number of the matched text")
             .action(ArgAction::SetTrue),
+        Arg::new("all_match")
+            .long("all-match")
+            .value_name("All Match")
+            .help("if this option is present only show files where every pattern matches somewhere")
+            .action(ArgAction::SetTrue),
+        Arg::new("and")
+            .long("and")
+            .value_name("And")
+            .help("if this option is present only show lines where every pattern matches")
+            .action(ArgAction::SetTrue),
+        Arg::new("path_match")
+            .long("path-match")
+            .short('p')
+            .value_name("Path Match")
+            .help("if this option is present match the patterns against the path of each file and directory instead of the contents of files")
+            .conflicts_with("vimgrep")
+            .action(ArgAction::SetTrue),
+        Arg::new("sort")
+            .long("sort")
+            .value_name("Sort By")
+            .help("order the entries of each directory from smallest to largest by path, modified, accessed, created, size or matches")
+            .value_parser(SORT_BY)
+            .action(ArgAction::Set),
+        Arg::new("sortr")
+            .long("sortr")
+            .value_name("Sort By")
+            .help("like --sort but from largest to smallest")
+            .value_parser(SORT_BY)
+            .conflicts_with("sort")
+            .action(ArgAction::Set),
+        Arg::new("replace")
+            .long("replace")
+            .short('r')
+            .value_name("Replacement")
+            .help("show each match replaced by this, $1, $name and ${name} are replaced by the groups of the match, files are not changed")
+            .action(ArgAction::Set),
+        Arg::new("write")
+            .long("write")
+            .value_name("Write")
+            .help("if this option is present write the replacements to the matched files and show the files and lines that changed")
+            .requires("replace")
+            .conflicts_with_all(["menu", "vimgrep"])
+            .action(ArgAction::SetTrue),
+        Arg::new("backup_suffix")
+            .long("backup-suffix")
+            .value_name("Backup Suffix")
+            .help("before writing a file copy it to its path with this added to the end, a file whose backup is already there is not written")
+            .requires("write")
+            .action(ArgAction::Set),
+        Arg::new("no_ignore")
+            .long("no-ignore")
+            .value_name("No Ignore")
+            .help("if this option is present don't use .gitignore, .ignore, .gretignore or the global ignore files, files given with --ignore-file are still used")
+            .action(ArgAction::SetTrue),
+        Arg::new("no_ignore_vcs")
+            .long("no-ignore-vcs")
+            .value_name("No Ignore VCS")
+            .help("if this option is present don't use .gitignore, .git/info/exclude or the global git ignore")
+            .action(ArgAction::SetTrue),
+        Arg::new("no_ignore_parent")
+            .long("no-ignore-parent")
+            .value_name("No Ignore Parent")
+            .help("if this option is present don't use ignore files in the directories above the target")
+            .action(ArgAction::SetTrue),
+        Arg::new("no_ignore_global")
+            .long("no-ignore-global")
+            .value_name("No Ignore Global")
+            .help("if this option is present don't use the global git ignore file")
+            .action(ArgAction::SetTrue),
+        Arg::new("ignore_file")
+            .long("ignore-file")
+            .value_name("Ignore File")
+            .help("also ignore the paths in this file, written like a .gitignore, can be given more than once")
+            .value_hint(ValueHint::FilePath)
+            .action(ArgAction::Append),
+        Arg::new("explain")
+            .long("explain")
+            .value_name("Explain Path")
+            .help("instead of searching, tell whether this path is searched and if not why it is skipped")
+            .value_hint(ValueHint::AnyPath)
+            .conflicts_with_all(["menu", "write"])
+            .action(ArgAction::Set),
+        Arg::new("debug")
+            .long("debug")
+            .value_name("Debug")
+            .help("if this option is present show every entry that was skipped and why after the results")
+            .action(ArgAction::SetTrue),
+        Arg::new("follow")
+            .long("follow")
+            .short('L')
+            .value_name("Follow")
+            .help("if this option is present walk into linked directories, links back to a directory they are inside of are skipped")
+            .action(ArgAction::SetTrue),
+        Arg::new("binary")
+            .long("binary")
+            .value_name("Binary Mode")
+            .help("what to do with binary files, skip them, report how many matches they have or search them as text with the unprintable bytes escaped")
+            .value_parser(BINARY)
+            .action(ArgAction::Set),
+        Arg::new("search_zip")
+            .long("search-zip")
+            .short('z')
+            .value_name("Search Zip")
+            .help("if this option is present search inside files compressed with gzip, bzip2, xz, zstd or lz4, running that program on them for all but gzip")
+            .action(ArgAction::SetTrue),
+        Arg::new("archives")
+            .long("archives")
+            .value_name("Archives")
+            .help("if this option is present search .zip, .jar, .war, .ear, .tar, .tar.gz and .tgz files as directories of the files inside of them")
+            .action(ArgAction::SetTrue),
+        Arg::new("pre")
+            .long("pre")
+            .value_name("Preprocessor")
+            .help("search what this command writes for each file instead of the file, it is given the path of the file and the file on stdin")
+            .value_hint(ValueHint::CommandName)
+            .action(ArgAction::Set),
+        Arg::new("pre_glob")
+            .long("pre-glob")
+            .value_name("Preprocessor Glob")
+            .help("only run --pre on files that match this glob, start it with ! to leave files out, can be given more than once")
+            .requires("pre")
+            .action(ArgAction::Append),
+        Arg::new("encoding")
+            .long("encoding")
+            .short('E')
+            .value_name("Encoding")
+            .help("decode files without a byte order mark from this encoding, any WHATWG label like utf-16le, shift_jis or latin1, files with one are always decoded")
+            .action(ArgAction::Set),
+        Arg::new("crlf")
+            .long("crlf")
+            .value_name("CRLF")
+            .help("if this option is present treat every file as having \\r\\n line endings, otherwise it is detected from the first line of each file")
+            .action(ArgAction::SetTrue),
+        Arg::new("trim")
+            .long("trim")
+            .value_name("Trim")
+            .help("if this option is present strip leading and trailing whitespace from the shown lines")
+            .action(ArgAction::SetTrue),
+        Arg::new("max_columns")
+            .long("max-columns")
+            .value_name("Max Columns")
+            .help("lines longer than this many bytes are replaced by a note of how many matches they had")
+            .action(ArgAction::Set),
+        Arg::new("max_columns_preview")
+            .long("max-columns-preview")
+            .value_name("Max Columns Preview")
+            .help("if this option is present show a window of --max-columns bytes around each match of a long line instead of leaving it out, unless the windows come to more than four times --max-columns")
+            .requires("max_columns")
+            .action(ArgAction::SetTrue),
+        Arg::new("vimgrep")
+            .long("vimgrep")
+            .value_name("Vimgrep")
+            .help("if this option is present print each match as path:line:column:text instead of a tree")
+            .conflicts_with_all(["menu", "just_files"])
+            .action(ArgAction::SetTrue),
         Arg::new("menu")
             .long("menu")
             .short('m')
@@ -95,14 +283,23 @@ fn add_expr_group(mut command: Command) -> Command {
             .action(ArgAction::Append),
     );
 
+    command = command.arg(
+        Arg::new("pattern_file")
+            .long("file")
+            .value_name("Pattern File")
+            .help("read patterns from this file, one per line, blank lines and lines starting with # are skipped, use - for stdin")
+            .value_hint(ValueHint::FilePath)
+            .action(ArgAction::Append),
+    );
+
     command = command.group(
         ArgGroup::new("expression_group")
             .id("expressions")
-            .args(["expression_pos", "expression"])
+            .args(["expression_pos", "expression", "pattern_file"])
             .multiple(true)
             .required(true),
     );
-    return command;
+    command
 }
 
 fn add_target_group(mut command: Command) -> Command {
@@ -129,5 +326,5 @@ fn add_target_group(mut command: Command) -> Command {
             .args(["target_pos", "target"]),
     );
 
-    return command;
+    command
 }
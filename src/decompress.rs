@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+// gzip files are decoded here, other compressed files are searched
+// through the program for their format, which reads the file on its
+// stdin and writes what it holds to stdout
+
+use crate::process::CommandOutput;
+use flate2::read::MultiGzDecoder;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::process::Command;
+
+/// How files with one of the extensions are decompressed.
+pub(crate) struct Decompressor {
+    extension: &'static str,
+    decoder: Decoder,
+}
+
+enum Decoder {
+    Gzip,
+    Program {
+        program: &'static str,
+        args: &'static [&'static str],
+    },
+}
+
+const DECOMPRESSORS: [Decompressor; 5] = [
+    Decompressor {
+        extension: "gz",
+        decoder: Decoder::Gzip,
+    },
+    Decompressor {
+        extension: "bz2",
+        decoder: Decoder::Program {
+            program: "bzip2",
+            args: &["-d", "-c"],
+        },
+    },
+    Decompressor {
+        extension: "xz",
+        decoder: Decoder::Program {
+            program: "xz",
+            args: &["-d", "-c"],
+        },
+    },
+    Decompressor {
+        extension: "zst",
+        decoder: Decoder::Program {
+            program: "zstd",
+            args: &["-q", "-d", "-c"],
+        },
+    },
+    Decompressor {
+        extension: "lz4",
+        decoder: Decoder::Program {
+            program: "lz4",
+            args: &["-d", "-c"],
+        },
+    },
+];
+
+/// the decompressor for the extension of `path`, `None` if it isn't compressed
+pub(crate) fn decompressor(path: &Path) -> Option<&'static Decompressor> {
+    let extension: &OsStr = path.extension()?;
+    DECOMPRESSORS.iter().find(|d| extension == d.extension)
+}
+
+impl Decompressor {
+    /// what `file` holds, decoding gzip in-process and running the program
+    /// for the other formats
+    pub(crate) fn open(&self, file: fs::File) -> io::Result<Box<dyn Read>> {
+        match self.decoder {
+            Decoder::Gzip => Ok(Box::new(MultiGzDecoder::new(BufReader::new(file)))),
+            Decoder::Program { program, args } => {
+                let mut command = Command::new(program);
+                command.args(args);
+                Ok(Box::new(CommandOutput::spawn(
+                    command,
+                    file,
+                    program.to_string(),
+                )?))
+            }
+        }
+    }
+}
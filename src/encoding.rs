@@ -0,0 +1,310 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+// files in other encodings are decoded to UTF-8 as they are read so the
+// patterns, line numbers and shown text work the same as for UTF-8 files
+
+use encoding_rs::{Decoder, UTF_16BE, UTF_16LE, UTF_8};
+use std::io::{self, Read};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+const READ_SIZE: usize = 64 * 1024;
+
+/// An encoding that files can be decoded from, any of the WHATWG
+/// encoding standard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Encoding(&'static encoding_rs::Encoding);
+
+impl Encoding {
+    /// the encoding named by `label`, the labels are those of the WHATWG
+    /// encoding standard, like `shift_jis` or `latin1`, and case is ignored
+    pub fn for_label(label: &str) -> Option<Encoding> {
+        encoding_rs::Encoding::for_label(label.as_bytes()).map(Encoding)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    /// the encoding of the byte order mark that `bytes` start with and its length
+    fn from_bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+        encoding_rs::Encoding::for_bom(bytes).map(|(encoding, len)| (Encoding(encoding), len))
+    }
+
+    fn bom(&self) -> &'static [u8] {
+        match self.0 {
+            e if e == UTF_8 => UTF8_BOM,
+            e if e == UTF_16LE => UTF16LE_BOM,
+            e if e == UTF_16BE => UTF16BE_BOM,
+            _ => b"",
+        }
+    }
+
+    /// invalid UTF-8 is left alone as it is for files that aren't decoded
+    /// instead of being replaced, so UTF-8 isn't decoded at all
+    fn decoder(&self) -> Option<Decoder> {
+        (self.0 != UTF_8).then(|| self.0.new_decoder_without_bom_handling())
+    }
+
+    /// the UTF-8 of a whole file without its byte order mark
+    pub(crate) fn decode(&self, input: &[u8]) -> Vec<u8> {
+        if self.0 == UTF_8 {
+            return input.to_vec();
+        }
+        self.0
+            .decode_without_bom_handling(input)
+            .0
+            .into_owned()
+            .into_bytes()
+    }
+
+    /// `text` in this encoding, `None` if it has a character that can't be
+    /// written in it
+    pub(crate) fn encode(&self, text: &[u8]) -> Option<Vec<u8>> {
+        if self.0 == UTF_8 {
+            return Some(text.to_vec());
+        }
+        let text: &str = std::str::from_utf8(text).ok()?;
+        // encoding_rs only decodes UTF-16
+        if self.0 == UTF_16LE {
+            return Some(text.encode_utf16().flat_map(u16::to_le_bytes).collect());
+        }
+        if self.0 == UTF_16BE {
+            return Some(text.encode_utf16().flat_map(u16::to_be_bytes).collect());
+        }
+        // like the replacement encoding, which can only be read
+        if self.0.output_encoding() != self.0 {
+            return None;
+        }
+        let (out, _, unmappable) = self.0.encode(text);
+        (!unmappable).then(|| out.into_owned())
+    }
+}
+
+/// Reads a file as UTF-8. The encoding comes from the byte order mark the
+/// file starts with, or else the one it is made with, without either the
+/// bytes are given out as they are.
+pub(crate) struct DecodeReader<R: Read> {
+    reader: R,
+    encoding: Option<Encoding>,
+    bom: bool,
+    // `None` when the bytes are given out as they are
+    decoder: Option<Decoder>,
+    // bytes read but not decoded yet
+    raw: Vec<u8>,
+    // bytes decoded but not given out yet, from `pos`
+    decoded: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read> DecodeReader<R> {
+    pub(crate) fn new(mut reader: R, encoding: Option<Encoding>) -> io::Result<DecodeReader<R>> {
+        let mut start: Vec<u8> = Vec::with_capacity(UTF8_BOM.len());
+        (&mut reader)
+            .take(UTF8_BOM.len() as u64)
+            .read_to_end(&mut start)?;
+        let (encoding, bom) = match Encoding::from_bom(&start) {
+            Some((found, len)) => {
+                start.drain(..len);
+                (Some(found), true)
+            }
+            None => (encoding, false),
+        };
+        Ok(DecodeReader {
+            reader,
+            encoding,
+            bom,
+            decoder: encoding.and_then(|encoding| encoding.decoder()),
+            raw: start,
+            decoded: Vec::new(),
+            pos: 0,
+            done: false,
+        })
+    }
+
+    /// the encoding the file is decoded from, `None` if it isn't
+    pub(crate) fn encoding(&self) -> Option<Encoding> {
+        self.encoding
+    }
+
+    /// whether the file started with a byte order mark
+    pub(crate) fn bom(&self) -> bool {
+        self.bom
+    }
+
+    fn fill(&mut self) -> io::Result<()> {
+        let decoder: &mut Decoder = match &mut self.decoder {
+            Some(decoder) => decoder,
+            // nothing to decode, the start of the file is given out first
+            None => {
+                self.decoded = std::mem::take(&mut self.raw);
+                if self.decoded.is_empty() {
+                    self.decoded.resize(READ_SIZE, 0);
+                    let n = self.reader.read(&mut self.decoded)?;
+                    self.decoded.truncate(n);
+                }
+                return Ok(());
+            }
+        };
+        self.decoded.clear();
+        while self.decoded.is_empty() && !self.done {
+            let len = self.raw.len();
+            self.raw.resize(len + READ_SIZE, 0);
+            let n = match self.reader.read(&mut self.raw[len..]) {
+                Ok(n) => n,
+                Err(e) => {
+                    self.raw.truncate(len);
+                    return Err(e);
+                }
+            };
+            self.raw.truncate(len + n);
+            self.done = n == 0;
+            // the decoder keeps the start of a character that continues in
+            // the next read, so all of the input is used with enough room
+            let room: usize = decoder
+                .max_utf8_buffer_length(self.raw.len())
+                .ok_or_else(|| io::Error::other("too many bytes to decode"))?;
+            self.decoded.resize(room, 0);
+            let (_, _, written, _) =
+                decoder.decode_to_utf8(&self.raw, &mut self.decoded, self.done);
+            self.decoded.truncate(written);
+            self.raw.clear();
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.decoded.len() {
+            self.pos = 0;
+            self.decoded.clear();
+            self.fill()?;
+        }
+        let n = buf.len().min(self.decoded.len() - self.pos);
+        buf[..n].copy_from_slice(&self.decoded[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// the contents of a whole file read as `encoding` as UTF-8, after its
+/// byte order mark when `bom` is set
+pub(crate) fn decode_file(bytes: &[u8], encoding: Encoding, bom: bool) -> Vec<u8> {
+    let start = if bom && bytes.starts_with(encoding.bom()) {
+        encoding.bom().len()
+    } else {
+        0
+    };
+    encoding.decode(&bytes[start..])
+}
+
+/// `text` written in `encoding` with its byte order mark when `bom` is set
+pub(crate) fn encode_file(text: &[u8], encoding: Encoding, bom: bool) -> Option<Vec<u8>> {
+    let mut out: Vec<u8> = Vec::new();
+    if bom {
+        out.extend_from_slice(encoding.bom());
+    }
+    out.extend(encoding.encode(text)?);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoding(label: &str) -> Encoding {
+        Encoding::for_label(label).unwrap()
+    }
+
+    /// gives out one byte on each read so characters are split between reads
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            (&mut self.0).take(1).read(buf)
+        }
+    }
+
+    fn read_all(bytes: &[u8], encoding: Option<Encoding>) -> (Vec<u8>, Option<Encoding>, bool) {
+        let mut reader = DecodeReader::new(OneByte(bytes), encoding).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        (out, reader.encoding(), reader.bom())
+    }
+
+    #[test]
+    fn whatwg_labels() {
+        assert_eq!(encoding("Shift_JIS").name(), "Shift_JIS");
+        assert_eq!(encoding("sjis"), encoding("shift_jis"));
+        assert_eq!(encoding(" LATIN1 ").name(), "windows-1252");
+        assert_eq!(encoding("ascii").name(), "windows-1252");
+        assert_eq!(encoding("utf-16").name(), "UTF-16LE");
+        assert_eq!(encoding("euc-kr").name(), "EUC-KR");
+        assert_eq!(Encoding::for_label("utf-7"), None);
+    }
+
+    #[test]
+    fn characters_split_between_reads() {
+        let sjis: &[u8] = b"\x93\xfa\x96\x7b\x8c\xea hello";
+        let (out, found, bom) = read_all(sjis, Some(encoding("shift_jis")));
+        assert_eq!(String::from_utf8(out).unwrap(), "日本語 hello");
+        assert_eq!(found, Some(encoding("shift_jis")));
+        assert!(!bom);
+
+        let utf16: &[u8] = b"\x3d\xd8\x00\xdea\x00";
+        let (out, _, _) = read_all(utf16, Some(encoding("utf-16le")));
+        assert_eq!(String::from_utf8(out).unwrap(), "😀a");
+    }
+
+    #[test]
+    fn byte_order_mark_wins() {
+        let (out, found, bom) = read_all(b"\xfe\xff\x00h\x00i", Some(encoding("shift_jis")));
+        assert_eq!(out, b"hi");
+        assert_eq!(found, Some(encoding("utf-16be")));
+        assert!(bom);
+    }
+
+    #[test]
+    fn utf8_is_left_alone() {
+        let bytes: &[u8] = b"a\xff\xfeb";
+        assert_eq!(read_all(bytes, None).0, bytes);
+        assert_eq!(read_all(bytes, Some(encoding("utf-8"))).0, bytes);
+        assert_eq!(encoding("utf-8").decode(bytes), bytes);
+    }
+
+    #[test]
+    fn invalid_bytes_are_replaced() {
+        assert_eq!(
+            encoding("utf-16le").decode(b"a\x00\x00\xdcb"),
+            "a\u{FFFD}\u{FFFD}".as_bytes()
+        );
+    }
+
+    #[test]
+    fn files_are_written_back() {
+        for (label, bom) in [
+            ("utf-8", true),
+            ("utf-16le", true),
+            ("utf-16be", false),
+            ("shift_jis", false),
+            ("euc-jp", false),
+        ] {
+            let text = "日本語 text\r\n".as_bytes();
+            let bytes: Vec<u8> = encode_file(text, encoding(label), bom).unwrap();
+            assert_eq!(Encoding::from_bom(&bytes).is_some(), bom, "{}", label);
+            assert_eq!(decode_file(&bytes, encoding(label), bom), text, "{}", label);
+        }
+        assert_eq!(
+            encode_file("€".as_bytes(), encoding("latin1"), false),
+            Some(b"\x80".to_vec())
+        );
+        assert_eq!(
+            encode_file("日".as_bytes(), encoding("latin1"), false),
+            None
+        );
+        assert_eq!(encode_file(b"a", encoding("iso-2022-kr"), false), None);
+    }
+}
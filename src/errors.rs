@@ -4,6 +4,7 @@ use crate::formats::error_prefix;
 use std::fmt;
 use std::path::PathBuf;
 
+#[derive(Debug)]
 pub enum Errors {
     PathNotFound { cause: PathBuf },
     IOError { cause: String },
@@ -11,6 +12,18 @@ pub enum Errors {
     InvalidRegex { cause: String },
     FailedToGetCWD,
     StringToUsizeFail { cause: String },
+    InvalidFileSize { cause: String },
+    Pcre2Unavailable,
+    FileChanged { cause: PathBuf },
+    UnknownEncoding { cause: String },
+    CantEncode { cause: String },
+    CompressedFile { cause: PathBuf },
+    ArchivedFile { cause: PathBuf },
+    PreprocessedFile { cause: PathBuf },
+    BinaryFile { cause: PathBuf },
+    BackupExists { cause: PathBuf },
+    PreprocessorNotFound { cause: PathBuf },
+    InvalidGlob { cause: String },
 }
 
 impl fmt::Display for Errors {
@@ -49,6 +62,95 @@ impl fmt::Display for Errors {
                     error_prefix, cause,
                 )
             }
+            Errors::InvalidFileSize { cause } => {
+                write!(
+                    f,
+                    "{}Failed to parse `{}` as a size, expected a number with an optional K, M or G suffix",
+                    error_prefix, cause,
+                )
+            }
+            Errors::Pcre2Unavailable => {
+                write!(
+                    f,
+                    "{}PCRE2 is not available in this build of gret, build it with `--features pcre2`",
+                    error_prefix,
+                )
+            }
+            Errors::FileChanged { cause } => {
+                write!(
+                    f,
+                    "{}`{}` changed since it was searched, it was not written",
+                    error_prefix,
+                    cause.display()
+                )
+            }
+            Errors::UnknownEncoding { cause } => {
+                write!(
+                    f,
+                    "{}Unknown encoding `{}`, expected a WHATWG encoding label like utf-8, utf-16le, shift_jis or latin1",
+                    error_prefix, cause,
+                )
+            }
+            Errors::CantEncode { cause } => {
+                write!(
+                    f,
+                    "{}Can't write {}, it has bytes that aren't valid in that encoding or a replacement has characters it can't hold, it was not written",
+                    error_prefix, cause,
+                )
+            }
+            Errors::CompressedFile { cause } => {
+                write!(
+                    f,
+                    "{}`{}` is compressed, it was not written",
+                    error_prefix,
+                    cause.display()
+                )
+            }
+            Errors::ArchivedFile { cause } => {
+                write!(
+                    f,
+                    "{}`{}` is inside of an archive, it was not written",
+                    error_prefix,
+                    cause.display()
+                )
+            }
+            Errors::PreprocessedFile { cause } => {
+                write!(
+                    f,
+                    "{}`{}` was searched through the preprocessor, it was not written",
+                    error_prefix,
+                    cause.display()
+                )
+            }
+            Errors::BinaryFile { cause } => {
+                write!(
+                    f,
+                    "{}`{}` is binary, it was not written",
+                    error_prefix,
+                    cause.display()
+                )
+            }
+            Errors::BackupExists { cause } => {
+                write!(
+                    f,
+                    "{}The backup `{}` already exists, the file was not written",
+                    error_prefix,
+                    cause.display()
+                )
+            }
+            Errors::PreprocessorNotFound { cause } => {
+                write!(
+                    f,
+                    "{}The preprocessor `{}` was not found",
+                    error_prefix,
+                    cause.display()
+                )
+            }
+            Errors::InvalidGlob { cause } => {
+                write!(f, "{}Invalid glob: {}", error_prefix, cause)
+            }
         }
     }
 }
+
+impl std::error::Error for Errors {}
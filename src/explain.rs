@@ -0,0 +1,791 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+// works out why an entry is left out of a search, whether the walker
+// gives an entry back is asked of the walker itself and the ignore files
+// are only read again to find the glob that is responsible, checked in
+// the order and against the paths that the ignore crate uses
+
+use crate::encoding::DecodeReader;
+use crate::errors::Errors;
+use crate::searcher::{
+    is_binary, is_vcs_dir, open_contents, walk_builder, BinaryMode, SearchOptions,
+    BINARY_PREFIX_LEN, IGNORE_FILE_NAME, VCS_DIRS,
+};
+use ignore::gitignore::{Gitignore, GitignoreBuilder, Glob};
+use ignore::Error as WalkError;
+use ignore::Match as IgnoreMatch;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Why an entry was left out of a search.
+#[derive(Debug, PartialEq)]
+pub enum SkipReason {
+    /// matched by `glob` as it is written in `file`, `line` counts from 1
+    Ignored {
+        file: Option<PathBuf>,
+        line: Option<usize>,
+        glob: String,
+    },
+    Hidden,
+    VcsDir,
+    MaxDepth,
+    TooLarge,
+    Binary,
+    ReadError(String),
+    OutsideTarget,
+    /// a link to `ancestor`, a directory it is already inside of
+    Loop {
+        ancestor: PathBuf,
+    },
+    /// inside of `link`, a link to a directory that isn't followed
+    InLink {
+        link: PathBuf,
+    },
+    /// the walker left it out but none of the reasons above apply
+    Unexplained,
+}
+
+/// An entry left out of a search and why.
+#[derive(Debug)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: SkipReason,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkipReason::Ignored { file, line, glob } => match (file, line) {
+                (Some(file), Some(line)) => write!(
+                    f,
+                    "ignored by `{}` on line {} of `{}`",
+                    glob,
+                    line,
+                    file.display()
+                ),
+                (Some(file), None) => write!(f, "ignored by `{}` in `{}`", glob, file.display()),
+                _ => write!(f, "ignored by `{}`", glob),
+            },
+            SkipReason::Hidden => write!(f, "it is hidden"),
+            SkipReason::VcsDir => write!(f, "it is a version control directory"),
+            SkipReason::MaxDepth => write!(f, "it is deeper than the max depth"),
+            SkipReason::TooLarge => write!(f, "it is larger than the max file size"),
+            SkipReason::Binary => write!(f, "it is a binary file"),
+            SkipReason::ReadError(e) => write!(f, "it couldn't be read, {}", e),
+            SkipReason::OutsideTarget => write!(f, "it is not inside of the target"),
+            SkipReason::Loop { ancestor } => write!(
+                f,
+                "it links back to `{}` which it is inside of",
+                ancestor.display()
+            ),
+            SkipReason::InLink { link } => write!(
+                f,
+                "it is inside of `{}`, a linked directory that isn't followed",
+                link.display()
+            ),
+            SkipReason::Unexplained => write!(f, "the walker left it out"),
+        }
+    }
+}
+
+/// Why `path` would be left out when searching `root`, `None` if it is searched.
+pub fn explain(
+    path: &Path,
+    root: &Path,
+    options: &SearchOptions,
+) -> Result<Option<SkipReason>, Errors> {
+    if !path.exists() {
+        return Err(Errors::PathNotFound {
+            cause: path.to_path_buf(),
+        });
+    }
+    let abs_root: PathBuf = absolute(root)?;
+    let abs_path: PathBuf = absolute(path)?;
+    // a target that is a file is always read
+    if abs_path == abs_root {
+        return Ok(if root.is_dir() {
+            None
+        } else {
+            file_reason(path, None, options)
+        });
+    }
+    let relative: &Path = match abs_path.strip_prefix(&abs_root) {
+        Ok(relative) => relative,
+        Err(_) => return Ok(Some(SkipReason::OutsideTarget)),
+    };
+    // the path as the walker gives it back
+    let target: PathBuf = root.join(relative);
+    let walked: Walked = walk(root, options, Some(target.clone()))?;
+    if walked.entries.contains(&target) {
+        return Ok(if entry_is_dir(&target, options) {
+            None
+        } else {
+            file_reason(path, options.max_filesize, options)
+        });
+    }
+    // the first entry on the way to `path` that the walker left out
+    let mut explainer = Explainer::new(options, root, abs_root);
+    let mut entry: PathBuf = root.to_path_buf();
+    for (i, component) in relative.components().enumerate() {
+        entry.push(component);
+        if walked.entries.contains(&entry) {
+            // without following, the walker doesn't go into links to directories
+            if !entry_is_dir(&entry, options) {
+                return Ok(Some(SkipReason::InLink { link: entry }));
+            }
+            continue;
+        }
+        if let Some(ancestor) = walked.loops.get(&entry) {
+            return Ok(Some(SkipReason::Loop {
+                ancestor: ancestor.clone(),
+            }));
+        }
+        let is_dir: bool = entry_is_dir(&entry, options);
+        let reason = explainer.entry_reason(&entry, i + 1, is_dir);
+        return Ok(Some(reason.unwrap_or(SkipReason::Unexplained)));
+    }
+    Ok(None)
+}
+
+/// Every entry under `root` that the walker leaves out, like ignored and
+/// hidden ones, the entries in a skipped directory aren't given on their
+/// own. Files left out once they are read come from
+/// [`crate::search_with_skipped`], this doesn't open any files.
+pub fn ignored(root: &Path, options: &SearchOptions) -> Result<Vec<Skipped>, Errors> {
+    let mut all: Vec<Skipped> = Vec::new();
+    if !root.is_dir() {
+        return Ok(all);
+    }
+    let walked: Walked = walk(root, options, None)?;
+    let mut explainer = Explainer::new(options, root, absolute(root)?);
+    let root_depth: usize = root.components().count();
+    for dir in walked.entries.iter() {
+        if !entry_is_dir(dir, options) {
+            continue;
+        }
+        // a directory that can't be read is reported by the search
+        let names: Vec<OsString> = match fs::read_dir(dir) {
+            Ok(read) => read.flatten().map(|entry| entry.file_name()).collect(),
+            Err(_) => continue,
+        };
+        for name in names {
+            let path: PathBuf = dir.join(name);
+            if walked.entries.contains(&path) || walked.loops.contains_key(&path) {
+                continue;
+            }
+            let depth: usize = path.components().count() - root_depth;
+            let is_dir: bool = entry_is_dir(&path, options);
+            let reason = explainer.entry_reason(&path, depth, is_dir);
+            all.push(Skipped {
+                path,
+                reason: reason.unwrap_or(SkipReason::Unexplained),
+            });
+        }
+    }
+    all.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(all)
+}
+
+/// What the walker gave back, the links it found to loop are by the link
+/// and give the directory they lead back to.
+struct Walked {
+    entries: HashSet<PathBuf>,
+    loops: HashMap<PathBuf, PathBuf>,
+}
+
+/// walk `root` like the search does, only going towards `target` when given
+fn walk(root: &Path, options: &SearchOptions, target: Option<PathBuf>) -> Result<Walked, Errors> {
+    let mut builder = walk_builder(root, options)?;
+    let all: bool = options.all;
+    builder.filter_entry(move |entry| {
+        (all || !is_vcs_dir(entry))
+            && target
+                .as_ref()
+                .is_none_or(|target| target.starts_with(entry.path()))
+    });
+    let mut walked = Walked {
+        entries: HashSet::new(),
+        loops: HashMap::new(),
+    };
+    for result in builder.build() {
+        match result {
+            Ok(entry) => {
+                walked.entries.insert(entry.into_path());
+            }
+            Err(e) => {
+                if let Some((ancestor, child)) = loop_of(&e) {
+                    walked
+                        .loops
+                        .insert(child.to_path_buf(), ancestor.to_path_buf());
+                }
+            }
+        }
+    }
+    Ok(walked)
+}
+
+/// An error from walking `root` as the entry it left out.
+pub(crate) fn walk_error(e: &WalkError, root: &Path) -> Skipped {
+    if let Some((ancestor, child)) = loop_of(e) {
+        return Skipped {
+            path: child.to_path_buf(),
+            reason: SkipReason::Loop {
+                ancestor: ancestor.to_path_buf(),
+            },
+        };
+    }
+    // the path is given on its own, so only the error inside is kept
+    let mut path: &Path = root;
+    let mut cause: &WalkError = e;
+    loop {
+        match cause {
+            WalkError::WithPath { path: p, err } => {
+                path = p;
+                cause = err;
+            }
+            WalkError::WithDepth { err, .. } | WalkError::WithLineNumber { err, .. } => {
+                cause = err;
+            }
+            _ => break,
+        }
+    }
+    Skipped {
+        path: path.to_path_buf(),
+        reason: SkipReason::ReadError(cause.to_string()),
+    }
+}
+
+/// the directory a link leads back to and the link, if `e` is a loop
+fn loop_of(e: &WalkError) -> Option<(&Path, &Path)> {
+    match e {
+        WalkError::Loop { ancestor, child } => Some((ancestor, child)),
+        WalkError::WithPath { err, .. }
+        | WalkError::WithDepth { err, .. }
+        | WalkError::WithLineNumber { err, .. } => loop_of(err),
+        _ => None,
+    }
+}
+
+/// a directory is only a link when following them
+fn entry_is_dir(path: &Path, options: &SearchOptions) -> bool {
+    if options.follow {
+        path.is_dir()
+    } else {
+        fs::symlink_metadata(path).is_ok_and(|m| m.is_dir())
+    }
+}
+
+/// why a file the walker gives back is dropped, `max_filesize` is
+/// only checked for files found while walking
+fn file_reason(
+    path: &Path,
+    max_filesize: Option<u64>,
+    options: &SearchOptions,
+) -> Option<SkipReason> {
+    if let Some(max) = max_filesize {
+        if fs::metadata(path).is_ok_and(|m| m.len() > max) {
+            return Some(SkipReason::TooLarge);
+        }
+    }
+    // only the names are looked at when matching paths
+    if options.path_match {
+        return None;
+    }
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Some(SkipReason::ReadError(e.to_string())),
+    };
+    let reader: Box<dyn Read> = match open_contents(path, file, options) {
+        Ok((reader, _)) => reader,
+        Err(e) => return Some(SkipReason::ReadError(e.to_string())),
+    };
+    if options.binary_mode() != BinaryMode::Skip {
+        return None;
+    }
+    let mut prefix: Vec<u8> = Vec::with_capacity(BINARY_PREFIX_LEN);
+    let read = DecodeReader::new(reader, options.encoding).and_then(|reader| {
+        reader
+            .take(BINARY_PREFIX_LEN as u64)
+            .read_to_end(&mut prefix)
+    });
+    match read {
+        Ok(_) if is_binary(&prefix) => Some(SkipReason::Binary),
+        Ok(_) => None,
+        Err(e) => Some(SkipReason::ReadError(e.to_string())),
+    }
+}
+
+fn absolute(path: &Path) -> Result<PathBuf, Errors> {
+    std::path::absolute(path).map_err(|e| Errors::IOError {
+        cause: format!(
+            "failed to get the absolute path of `{}`: {}",
+            path.display(),
+            e
+        ),
+    })
+}
+
+/// The ignore files in one directory.
+struct DirIgnores {
+    custom: Gitignore,
+    ignore: Gitignore,
+    git: Gitignore,
+    exclude: Gitignore,
+    has_git: bool,
+}
+
+/// Checks entries of the search of `root`, reading the ignore files
+/// of each directory only once. Entries are given as the walker gives
+/// them, starting with `root` as it was written.
+struct Explainer<'a> {
+    options: &'a SearchOptions,
+    root: PathBuf,
+    abs_root: PathBuf,
+    no_ignore: bool,
+    vcs: bool,
+    dirs: HashMap<PathBuf, DirIgnores>,
+    global: Gitignore,
+    explicit: Vec<Gitignore>,
+}
+
+enum Verdict {
+    Ignored(SkipReason),
+    Whitelisted,
+    Unmatched,
+}
+
+impl<'a> Explainer<'a> {
+    fn new(options: &'a SearchOptions, root: &Path, abs_root: PathBuf) -> Explainer<'a> {
+        let no_ignore: bool = options.no_ignore || options.all;
+        let vcs: bool = !no_ignore && !options.no_ignore_vcs;
+        let global: Gitignore = if vcs && !options.no_ignore_global {
+            Gitignore::global().0
+        } else {
+            Gitignore::empty()
+        };
+        let explicit: Vec<Gitignore> = options
+            .ignore_files
+            .iter()
+            .map(|path| {
+                let mut builder = GitignoreBuilder::new("");
+                builder.add(path);
+                builder.build().unwrap_or_else(|_| Gitignore::empty())
+            })
+            .collect();
+        Explainer {
+            options,
+            root: root.to_path_buf(),
+            abs_root,
+            no_ignore,
+            vcs,
+            dirs: HashMap::new(),
+            global,
+            explicit,
+        }
+    }
+
+    /// why the walker wouldn't give back the entry at `path`, `depth` is
+    /// 1 for an entry directly in the root
+    fn entry_reason(&mut self, path: &Path, depth: usize, is_dir: bool) -> Option<SkipReason> {
+        if self.options.max_depth.is_some_and(|max| depth > max) {
+            return Some(SkipReason::MaxDepth);
+        }
+        let whitelisted: bool = match self.matched(path, is_dir) {
+            Verdict::Ignored(reason) => return Some(reason),
+            Verdict::Whitelisted => true,
+            Verdict::Unmatched => false,
+        };
+        let name = path.file_name().unwrap_or_default();
+        let searching_hidden: bool = self.options.hidden || self.options.all;
+        if !whitelisted && !searching_hidden && name.as_encoded_bytes().starts_with(b".") {
+            return Some(SkipReason::Hidden);
+        }
+        if !self.options.all && is_dir && VCS_DIRS.iter().any(|vcs| name == *vcs) {
+            return Some(SkipReason::VcsDir);
+        }
+        None
+    }
+
+    /// the directories whose ignore files apply to `abs_path`, nearest first
+    fn chain(&self, abs_path: &Path) -> Vec<PathBuf> {
+        let parents = !self.no_ignore && !self.options.no_ignore_parent;
+        abs_path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| parents || dir.starts_with(&self.abs_root))
+            .map(Path::to_path_buf)
+            .collect()
+    }
+
+    fn matched(&mut self, path: &Path, is_dir: bool) -> Verdict {
+        // the ignore files of directories are matched with the absolute
+        // path, which each one strips its own directory from, while the
+        // global and explicit ones get the path as it is walked without
+        // a leading `./`, like the ignore crate does
+        let abs_path: PathBuf = self
+            .abs_root
+            .join(path.strip_prefix(&self.root).unwrap_or(path));
+        let walked: &Path = path.strip_prefix("./").unwrap_or(path);
+        let chain: Vec<PathBuf> = self.chain(&abs_path);
+        for dir in chain.iter() {
+            if !self.dirs.contains_key(dir) {
+                let ignores = self.read_dir_ignores(dir);
+                self.dirs.insert(dir.clone(), ignores);
+            }
+        }
+        let chain: Vec<&DirIgnores> = chain.iter().map(|dir| &self.dirs[dir]).collect();
+
+        let any_git: bool = chain.iter().any(|dir| dir.has_git);
+        let (mut m_custom, mut m_ignore, mut m_git, mut m_exclude) = (
+            IgnoreMatch::None,
+            IgnoreMatch::None,
+            IgnoreMatch::None,
+            IgnoreMatch::None,
+        );
+        // ignore files above the repository a path is in don't apply to it
+        let mut saw_git: bool = false;
+        for dir in chain {
+            if m_custom.is_none() {
+                m_custom = dir.custom.matched(&abs_path, is_dir);
+            }
+            if m_ignore.is_none() {
+                m_ignore = dir.ignore.matched(&abs_path, is_dir);
+            }
+            if any_git && !saw_git && m_git.is_none() {
+                m_git = dir.git.matched(&abs_path, is_dir);
+            }
+            if any_git && !saw_git && m_exclude.is_none() {
+                m_exclude = dir.exclude.matched(&abs_path, is_dir);
+            }
+            saw_git = saw_git || dir.has_git;
+        }
+        let m_global = if any_git {
+            self.global.matched(walked, is_dir)
+        } else {
+            IgnoreMatch::None
+        };
+        let m_explicit = self
+            .explicit
+            .iter()
+            .rev()
+            .map(|gi| gi.matched(walked, is_dir))
+            .find(|m| !m.is_none())
+            .unwrap_or(IgnoreMatch::None);
+
+        match m_custom
+            .or(m_ignore)
+            .or(m_git)
+            .or(m_exclude)
+            .or(m_global)
+            .or(m_explicit)
+        {
+            IgnoreMatch::Ignore(glob) => Verdict::Ignored(ignored_by(glob)),
+            IgnoreMatch::Whitelist(_) => Verdict::Whitelisted,
+            IgnoreMatch::None => Verdict::Unmatched,
+        }
+    }
+
+    fn read_dir_ignores(&self, dir: &Path) -> DirIgnores {
+        let read = |enabled: bool, file: PathBuf| {
+            if !enabled || !file.is_file() {
+                return Gitignore::empty();
+            }
+            let mut builder = GitignoreBuilder::new(dir);
+            builder.add(file);
+            builder.build().unwrap_or_else(|_| Gitignore::empty())
+        };
+        DirIgnores {
+            custom: read(!self.no_ignore, dir.join(IGNORE_FILE_NAME)),
+            ignore: read(!self.no_ignore, dir.join(".ignore")),
+            git: read(self.vcs, dir.join(".gitignore")),
+            exclude: read(self.vcs, dir.join(".git").join("info").join("exclude")),
+            has_git: self.vcs && dir.join(".git").exists(),
+        }
+    }
+}
+
+fn ignored_by(glob: &Glob) -> SkipReason {
+    let file: Option<PathBuf> = glob.from().map(Path::to_path_buf);
+    // the glob keeps the text of its line but not where it was
+    let line: Option<usize> = file
+        .as_ref()
+        .and_then(|file| fs::read_to_string(file).ok())
+        .and_then(|contents| {
+            contents
+                .lines()
+                .position(|line| line.trim_end() == glob.original().trim_end())
+        })
+        .map(|i| i + 1);
+    SkipReason::Ignored {
+        file,
+        line,
+        glob: glob.original().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::searcher::{search_with_skipped, Searched};
+    use tempfile::TempDir;
+
+    const PATTERN: &str = "hello";
+
+    fn put(root: &Path, name: &str, contents: &str) {
+        let path: PathBuf = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// files that hold the pattern
+    fn files(root: &Path, names: &[&str]) {
+        for name in names {
+            put(root, name, PATTERN);
+        }
+    }
+
+    fn options() -> SearchOptions {
+        SearchOptions::new([PATTERN]).unwrap()
+    }
+
+    /// every file under `dir` that holds the pattern, links aren't followed
+    fn with_pattern(dir: &Path, all: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap().flatten() {
+            let path: PathBuf = entry.path();
+            let file_type = entry.file_type().unwrap();
+            if file_type.is_dir() {
+                with_pattern(&path, all);
+            } else if file_type.is_file()
+                && fs::read_to_string(&path).is_ok_and(|c| c.contains(PATTERN))
+            {
+                all.push(path);
+            }
+        }
+    }
+
+    /// check that `explain` and `--debug` agree with the search on each
+    /// file, the reasons are given by the path inside of `root`
+    fn check(root: &Path, options: &SearchOptions) -> HashMap<PathBuf, SkipReason> {
+        let (searched, mut skipped) = search_with_skipped(&[root], options).unwrap();
+        let found: HashSet<PathBuf> = match searched {
+            Searched::Dir(dirs) => dirs
+                .into_iter()
+                .flat_map(|dir| dir.files)
+                .map(|file| file.path)
+                .collect(),
+            _ => panic!("`{}` wasn't searched as a directory", root.display()),
+        };
+        skipped.extend(ignored(root, options).unwrap());
+        let mut all: Vec<PathBuf> = Vec::new();
+        with_pattern(root, &mut all);
+        let mut reasons: HashMap<PathBuf, SkipReason> = HashMap::new();
+        for path in all {
+            let reason: Option<SkipReason> = explain(&path, root, options).unwrap();
+            assert_eq!(
+                reason.is_none(),
+                found.contains(&path),
+                "`{}` explained as {:?}",
+                path.display(),
+                reason
+            );
+            let listed: bool = skipped.iter().any(|skip| path.starts_with(&skip.path));
+            assert_eq!(listed, reason.is_some(), "`{}` in --debug", path.display());
+            if let Some(reason) = reason {
+                reasons.insert(path.strip_prefix(root).unwrap().to_path_buf(), reason);
+            }
+        }
+        reasons
+    }
+
+    fn ignored_by(file: PathBuf, line: usize, glob: &str) -> SkipReason {
+        SkipReason::Ignored {
+            file: Some(file),
+            line: Some(line),
+            glob: glob.to_string(),
+        }
+    }
+
+    #[test]
+    fn ignore_files() {
+        let dir = TempDir::new().unwrap();
+        let root: &Path = dir.path();
+        files(root, &["a.txt", "b.txt", "c.txt", "sub/a.txt"]);
+        put(root, ".ignore", "a.txt\n");
+        put(root, IGNORE_FILE_NAME, "# comment\nb.txt\n");
+        put(root, "sub/.ignore", "!a.txt\n");
+        let reasons = check(root, &options());
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(
+            reasons[Path::new("a.txt")],
+            ignored_by(root.join(".ignore"), 1, "a.txt")
+        );
+        assert_eq!(
+            reasons[Path::new("b.txt")],
+            ignored_by(root.join(IGNORE_FILE_NAME), 2, "b.txt")
+        );
+        assert!(check(root, &options().no_ignore(true)).is_empty());
+    }
+
+    #[test]
+    fn gitignore_needs_a_repository() {
+        let dir = TempDir::new().unwrap();
+        let root: &Path = dir.path();
+        files(root, &["a.log", "keep.log", "ex.txt", "b.txt"]);
+        put(root, ".gitignore", "*.log\n!keep.log\n");
+        assert!(check(root, &options()).is_empty());
+
+        put(root, ".git/info/exclude", "ex.txt\n");
+        let reasons = check(root, &options());
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(
+            reasons[Path::new("a.log")],
+            ignored_by(root.join(".gitignore"), 1, "*.log")
+        );
+        assert_eq!(
+            reasons[Path::new("ex.txt")],
+            ignored_by(root.join(".git/info/exclude"), 1, "ex.txt")
+        );
+        assert!(check(root, &options().no_ignore_vcs(true)).is_empty());
+    }
+
+    #[test]
+    fn hidden_and_vcs() {
+        let dir = TempDir::new().unwrap();
+        let root: &Path = dir.path();
+        files(root, &[".a.txt", ".dir/b.txt", ".git/c.txt", "d.txt"]);
+        let reasons = check(root, &options());
+        assert_eq!(reasons[Path::new(".a.txt")], SkipReason::Hidden);
+        assert_eq!(reasons[Path::new(".dir/b.txt")], SkipReason::Hidden);
+        assert_eq!(reasons.len(), 3);
+
+        let reasons = check(root, &options().hidden(true));
+        assert_eq!(reasons[Path::new(".git/c.txt")], SkipReason::VcsDir);
+        assert_eq!(reasons.len(), 1);
+        assert!(check(root, &options().all(true)).is_empty());
+    }
+
+    #[test]
+    fn max_depth() {
+        let dir = TempDir::new().unwrap();
+        let root: &Path = dir.path();
+        files(root, &["a.txt", "b/c.txt", "b/d/e.txt"]);
+        let reasons = check(root, &options().max_depth(Some(2)));
+        assert_eq!(reasons.len(), 1);
+        assert_eq!(reasons[Path::new("b/d/e.txt")], SkipReason::MaxDepth);
+    }
+
+    #[test]
+    fn parent_ignore_files() {
+        let dir = TempDir::new().unwrap();
+        files(dir.path(), &["sub/a.txt", "sub/b.txt", "sub/c.txt"]);
+        put(dir.path(), ".ignore", "a.txt\nsub/b.txt\n");
+        let root: PathBuf = dir.path().join("sub");
+        let reasons = check(&root, &options());
+        assert_eq!(reasons.len(), 2);
+        assert_eq!(
+            reasons[Path::new("b.txt")],
+            ignored_by(dir.path().join(".ignore"), 2, "sub/b.txt")
+        );
+        assert!(check(&root, &options().no_ignore_parent(true)).is_empty());
+    }
+
+    /// `path` relative to the working directory, through `..` where it
+    /// isn't under it
+    fn relative_to_cwd(path: &Path) -> PathBuf {
+        let cwd: PathBuf = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let path: PathBuf = path.canonicalize().unwrap();
+        let shared: usize = cwd
+            .components()
+            .zip(path.components())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut relative: PathBuf = PathBuf::new();
+        for _ in cwd.components().skip(shared) {
+            relative.push("..");
+        }
+        relative.extend(path.components().skip(shared));
+        relative
+    }
+
+    #[test]
+    fn explicit_ignore_file_with_a_relative_root() {
+        // the walker matches these against the path as it is walked, so
+        // the root has to be relative to the working directory
+        let dir = TempDir::new().unwrap();
+        let relative: PathBuf = relative_to_cwd(dir.path());
+        let name: &str = relative.to_str().unwrap();
+        files(dir.path(), &["x.txt", "y.txt", "sub/z.txt"]);
+        let ignore_file: PathBuf = dir.path().join("ign");
+        fs::write(&ignore_file, format!("{}/x.txt\nz.txt\n", name)).unwrap();
+        let options = options().ignore_files(vec![ignore_file.clone()]);
+        for root in [relative.clone(), Path::new(".").join(&relative)] {
+            let reasons = check(&root, &options);
+            assert_eq!(reasons.len(), 2);
+            assert_eq!(
+                reasons[Path::new("x.txt")],
+                ignored_by(ignore_file.clone(), 1, &format!("{}/x.txt", name))
+            );
+            assert_eq!(
+                reasons[Path::new("sub/z.txt")],
+                ignored_by(ignore_file.clone(), 2, "z.txt")
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_back_to_a_parent() {
+        let dir = TempDir::new().unwrap();
+        let root: &Path = dir.path();
+        files(root, &["d/a.txt"]);
+        let link: PathBuf = root.join("d/back");
+        std::os::unix::fs::symlink("..", &link).unwrap();
+        let inside: PathBuf = link.join("d/a.txt");
+
+        let follow = options().follow(true);
+        assert!(check(root, &follow).is_empty());
+        let (_, skipped) = search_with_skipped(&[root], &follow).unwrap();
+        let looped = SkipReason::Loop {
+            ancestor: root.to_path_buf(),
+        };
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].path, link);
+        assert_eq!(skipped[0].reason, looped);
+        assert_eq!(explain(&inside, root, &follow).unwrap(), Some(looped));
+
+        let (_, skipped) = search_with_skipped(&[root], &options()).unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(
+            explain(&inside, root, &options()).unwrap(),
+            Some(SkipReason::InLink { link })
+        );
+    }
+
+    #[test]
+    fn search_reports_files_it_leaves_out() {
+        let dir = TempDir::new().unwrap();
+        let root: &Path = dir.path();
+        files(root, &["a.txt"]);
+        put(root, "bin", "hello\0");
+        put(root, "big.txt", &PATTERN.repeat(100));
+        let options = options().max_filesize(Some(50));
+        let (_, skipped) = search_with_skipped(&[root], &options).unwrap();
+        let reason = |name: &str| {
+            skipped
+                .iter()
+                .find(|skip| skip.path == root.join(name))
+                .map(|skip| &skip.reason)
+        };
+        assert_eq!(reason("bin"), Some(&SkipReason::Binary));
+        assert_eq!(reason("big.txt"), Some(&SkipReason::TooLarge));
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(
+            explain(&root.join("big.txt"), root, &options).unwrap(),
+            Some(SkipReason::TooLarge)
+        );
+    }
+}
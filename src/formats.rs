@@ -15,6 +15,8 @@ const GREEN_FG: SetForegroundColor = SetForegroundColor(Color::Green);
 pub const LINE_NUMBER_FG: SetForegroundColor = SetForegroundColor(Color::Yellow);
 pub const DEFAULT_FG: SetForegroundColor = SetForegroundColor(Color::White);
 const MAGENTA_FG: SetForegroundColor = SetForegroundColor(Color::Magenta);
+// for text matched by more than one pattern
+const OVERLAP_FG: SetForegroundColor = SetForegroundColor(Color::DarkYellow);
 
 const NEW_LINE: &str = "\n";
 // the extra space is for cursor being 1 in
@@ -28,6 +30,7 @@ pub const BRANCH_HAS_NEXT: &str = "├──";
 pub const BRANCH_END: &str = "└──";
 pub const VER_LINE_SPACER: &str = "│  ";
 pub const SPACER: &str = "   ";
+pub const ELLIPSIS: &str = "…";
 
 const MATCHED_COLORS: [SetForegroundColor; 3] = [GREEN_FG, MAGENTA_FG, RED_FG];
 
@@ -50,6 +53,13 @@ pub fn get_color(i: usize) -> SetForegroundColor {
     MATCHED_COLORS[i % MATCHED_COLORS.len()]
 }
 
+pub fn get_segment_color(regex_ids: &[usize]) -> SetForegroundColor {
+    match regex_ids {
+        [id] => get_color(*id),
+        _ => OVERLAP_FG,
+    }
+}
+
 pub fn dir_name(name: &str) -> StyledContent<&str> {
     name.with(Color::Blue).attribute(Attribute::Bold)
 }
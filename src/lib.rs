@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+//! gret (global regular expression tree) searches a directory or file
+//! for regex patterns and arranges the matches in a tree.
+//!
+//! ```no_run
+//! use gret::{search, SearchOptions, TreePrinter};
+//!
+//! let options = SearchOptions::new(["TODO", "FIXME"])?.max_depth(Some(3));
+//! let searched = search(&["src"], &options)?;
+//! TreePrinter::new()
+//!     .line_number(true)
+//!     .write(&mut std::io::stdout(), &searched)
+//!     .unwrap();
+//! # Ok::<(), gret::Errors>(())
+//! ```
+
+mod archive;
+mod decompress;
+mod encoding;
+mod errors;
+mod explain;
+pub mod formats;
+mod lines;
+mod matcher;
+#[cfg(feature = "pcre2")]
+mod pcre2;
+mod printer;
+mod process;
+mod searcher;
+mod tree;
+mod writer;
+
+pub use archive::ArchiveEntry;
+pub use encoding::Encoding;
+pub use errors::Errors;
+pub use explain::{explain, ignored, SkipReason, Skipped};
+pub use printer::TreePrinter;
+pub use searcher::{
+    search, search_with_skipped, BinaryMode, Directory, File, Match, MatchedLine, SearchOptions,
+    Searched, Segment, SortBy,
+};
+pub use tree::{Node, NodeKind, Nodes};
+pub use writer::write_replacements;
@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+use memchr::memrchr;
+use std::io::{self, Read};
+
+const INITIAL_CAPACITY: usize = 64 * 1024;
+
+/// Reads from `reader` into a bounded buffer and hands out blocks made
+/// of whole lines so a file never has to be held in memory at once.
+/// The buffer only grows past its initial size for a line that is longer.
+pub(crate) struct LineBuffer<R: Read> {
+    reader: R,
+    buf: Vec<u8>,
+    // start and end of the bytes read but not yet given out
+    pos: usize,
+    end: usize,
+}
+
+impl<R: Read> LineBuffer<R> {
+    pub(crate) fn new(reader: R) -> LineBuffer<R> {
+        LineBuffer {
+            reader,
+            buf: vec![0; INITIAL_CAPACITY],
+            pos: 0,
+            end: 0,
+        }
+    }
+
+    /// the next block, it ends with a `\n` unless it is the end of the file
+    pub(crate) fn next_block(&mut self) -> io::Result<Option<&[u8]>> {
+        self.buf.copy_within(self.pos..self.end, 0);
+        self.end -= self.pos;
+        self.pos = 0;
+        loop {
+            if self.end == self.buf.len() {
+                self.buf.resize(self.buf.len() * 2, 0);
+            }
+            let n = match self.reader.read(&mut self.buf[self.end..]) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if n == 0 {
+                if self.end == 0 {
+                    return Ok(None);
+                }
+                self.pos = self.end;
+                return Ok(Some(&self.buf[..self.end]));
+            }
+            let searched_to = self.end;
+            self.end += n;
+            if let Some(i) = memrchr(b'\n', &self.buf[searched_to..self.end]) {
+                self.pos = searched_to + i + 1;
+                return Ok(Some(&self.buf[..self.pos]));
+            }
+        }
+    }
+}
@@ -2,9 +2,6 @@
 
 // TODO put apple developer certificate so downloads from releases can be done
 // TODO put the line in the Selected struct around an os check
-// TODO change the hidden option to still not searh .git directory i think or make that a seperate
-// option, --hidden or -h to show . and -a or --all to search all files
-// don't think there is a use case to search .git along with other files
 // TODO fix completions
 // TODO On highlighting for the menu had to overwrite the default
 // fg to be white so that the background styling wouldn't
@@ -14,19 +11,14 @@
 
 mod args;
 mod command;
-mod errors;
-mod formats;
 mod logger;
 mod menu;
-mod printer;
-mod searcher;
 use args::{parse_args, Config};
-use errors::Errors;
+use gret::{Errors, Searched, SkipReason, Skipped};
 use lazy_static::lazy_static;
 use menu::Menu;
-use printer::write_results;
-use searcher::Searched;
 use std::io::{stdout, StdoutLock};
+use std::path::Path;
 
 lazy_static! {
     static ref CONFIG: Config = parse_args().unwrap_or_else(|e| {
@@ -36,30 +28,52 @@ lazy_static! {
 
 fn main() {
     let mut out: StdoutLock = stdout().lock();
-    if CONFIG.is_dir {
-        let directories =
-            searcher::search_dir(CONFIG.path.clone()).unwrap_or_else(|e| exit_error(e));
-        if CONFIG.menu {
-            // only open the cli if there were matches
-            if directories.get(0).unwrap().children.len() > 0
-                || directories.get(0).unwrap().files.len() > 0
-            {
-                start_menu(&mut out, Searched::Dir(directories));
-            }
-        } else {
-            print_results(&mut out, Searched::Dir(directories));
+    if let Some(path) = &CONFIG.explain {
+        explain(path);
+        return;
+    }
+    let (searched, skipped): (Searched, Vec<Skipped>) =
+        gret::search_with_skipped(&[&CONFIG.path], &CONFIG.search)
+            .unwrap_or_else(|e| exit_error(e));
+    if CONFIG.menu {
+        // only open the cli if there were matches
+        if !searched.is_empty() {
+            start_menu(&mut out, searched);
         }
+    } else if CONFIG.write {
+        write_replacements(&mut out, searched);
+    } else {
+        print_results(&mut out, searched);
+    }
+    if CONFIG.debug {
+        print_skipped(skipped);
     } else {
-        if let Some(file) =
-            searcher::search_file(CONFIG.path.clone()).unwrap_or_else(|e| exit_error(e))
-        {
-            if CONFIG.menu {
-                if file.lines.len() > 0 {
-                    start_menu(&mut out, Searched::File(file));
-                }
-            } else {
-                print_results(&mut out, Searched::File(file));
-            }
+        print_loops(&skipped);
+    }
+}
+
+fn explain(path: &Path) {
+    match gret::explain(path, &CONFIG.path, &CONFIG.search) {
+        Ok(Some(reason)) => println!("`{}` is skipped, {}", path.display(), reason),
+        Ok(None) => println!("`{}` is searched", path.display()),
+        Err(e) => exit_error(e),
+    }
+}
+
+/// what the search left out along with what the walker left out
+fn print_skipped(mut skipped: Vec<Skipped>) {
+    skipped.extend(gret::ignored(&CONFIG.path, &CONFIG.search).unwrap_or_else(|e| exit_error(e)));
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    for skip in skipped {
+        eprintln!("skipped `{}`, {}", skip.path.display(), skip.reason);
+    }
+}
+
+/// links that lead back to a directory they are inside of are always told
+fn print_loops(skipped: &[Skipped]) {
+    for skip in skipped {
+        if let SkipReason::Loop { .. } = skip.reason {
+            eprintln!("skipped `{}`, {}", skip.path.display(), skip.reason);
         }
     }
 }
@@ -73,13 +87,25 @@ fn start_menu(out: &mut StdoutLock, res: Searched) {
 }
 
 fn print_results(out: &mut StdoutLock, searched: Searched) {
-    write_results(out, &searched).unwrap_or_else(|e| {
+    CONFIG.printer.write(out, &searched).unwrap_or_else(|e| {
         exit_error(Errors::IOError {
             cause: e.to_string(),
         })
     });
 }
 
+fn write_replacements(out: &mut StdoutLock, mut searched: Searched) {
+    let errors: Vec<Errors> =
+        gret::write_replacements(&mut searched, CONFIG.backup_suffix.as_deref());
+    print_results(out, searched);
+    if !errors.is_empty() {
+        for e in errors {
+            println!("{}", e);
+        }
+        std::process::exit(1);
+    }
+}
+
 fn exit_error(e: Errors) -> ! {
     println!("{}", e);
     std::process::exit(1);
@@ -0,0 +1,192 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+use crate::errors::Errors;
+use regex::bytes::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use regex_syntax::hir::Look;
+use regex_syntax::ParserBuilder;
+use std::io;
+
+/// A compiled pattern from one of the regex engines.
+pub(crate) trait Matcher: Send + Sync {
+    /// start and end of the leftmost match starting at or after `start`,
+    /// text before `start` is still seen by look behind and `\b`, an error
+    /// is an engine failing to finish, like PCRE2 hitting its match limit
+    fn find_at(&self, haystack: &[u8], start: usize) -> io::Result<Option<(usize, usize)>>;
+
+    /// every match that doesn't overlap another, an empty match right
+    /// after the previous one is skipped
+    fn find_iter(&self, haystack: &[u8]) -> io::Result<Vec<(usize, usize)>> {
+        let mut found: Vec<(usize, usize)> = Vec::new();
+        let mut pos: usize = 0;
+        let mut last_end: Option<usize> = None;
+        while pos <= haystack.len() {
+            let (start, end) = match self.find_at(haystack, pos)? {
+                Some(m) => m,
+                None => break,
+            };
+            if start == end {
+                // past the whole character so a search isn't started inside of it
+                pos = end + char_len(&haystack[end..]);
+                if last_end == Some(end) {
+                    continue;
+                }
+            } else {
+                pos = end;
+            }
+            last_end = Some(end);
+            found.push((start, end));
+        }
+        Ok(found)
+    }
+
+    /// append `template` to `dst` with `$1`, `$name`, `${name}` replaced by
+    /// the groups of the match found at `start`, and `$$` by `$`
+    fn expand_at(
+        &self,
+        haystack: &[u8],
+        start: usize,
+        template: &str,
+        dst: &mut Vec<u8>,
+    ) -> io::Result<()>;
+}
+
+/// the length of the UTF-8 character `bytes` start with, 1 for a byte
+/// that doesn't start one
+fn char_len(bytes: &[u8]) -> usize {
+    let len = match bytes.first() {
+        Some(0xC0..=0xDF) => 2,
+        Some(0xE0..=0xEF) => 3,
+        Some(0xF0..=0xF7) => 4,
+        _ => return 1,
+    };
+    match bytes.get(..len).map(std::str::from_utf8) {
+        Some(Ok(_)) => len,
+        _ => 1,
+    }
+}
+
+impl Matcher for Regex {
+    fn find_at(&self, haystack: &[u8], start: usize) -> io::Result<Option<(usize, usize)>> {
+        Ok(Regex::find_at(self, haystack, start).map(|m| (m.start(), m.end())))
+    }
+
+    fn expand_at(
+        &self,
+        haystack: &[u8],
+        start: usize,
+        template: &str,
+        dst: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        if let Some(caps) = self.captures_at(haystack, start) {
+            caps.expand(template.as_bytes(), dst);
+        }
+        Ok(())
+    }
+
+    fn find_iter(&self, haystack: &[u8]) -> io::Result<Vec<(usize, usize)>> {
+        Ok(Regex::find_iter(self, haystack)
+            .map(|m| (m.start(), m.end()))
+            .collect())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Engine {
+    Regex,
+    #[cfg(feature = "pcre2")]
+    Pcre2,
+}
+
+/// The patterns compiled by one engine, in the order they were given.
+pub(crate) struct Patterns {
+    pub(crate) matchers: Vec<Box<dyn Matcher>>,
+    // all of the patterns, to check a whole block at once
+    set: Option<RegexSet>,
+    // the patterns that can only be run on each line on its own
+    line_by_line: Vec<bool>,
+}
+
+impl Patterns {
+    /// `crlf` makes `\r\n` a line terminator for `^` and `$`
+    pub(crate) fn new(sources: &[String], engine: Engine, crlf: bool) -> Result<Patterns, Errors> {
+        match engine {
+            Engine::Regex => {
+                // multi line so that `^` and `$` still match at each line when searching a block
+                let matchers = sources
+                    .iter()
+                    .map(|p| {
+                        RegexBuilder::new(p)
+                            .multi_line(true)
+                            .crlf(crlf)
+                            .build()
+                            .map(|r| Box::new(r) as Box<dyn Matcher>)
+                            .map_err(|_| Errors::InvalidRegex { cause: p.clone() })
+                    })
+                    .collect::<Result<Vec<Box<dyn Matcher>>, Errors>>()?;
+                let set = RegexSetBuilder::new(sources)
+                    .multi_line(true)
+                    .crlf(crlf)
+                    .build()
+                    .map_err(|e| Errors::InvalidRegex {
+                        cause: e.to_string(),
+                    })?;
+                Ok(Patterns {
+                    matchers,
+                    set: Some(set),
+                    line_by_line: sources.iter().map(|p| anchors_text(p)).collect(),
+                })
+            }
+            #[cfg(feature = "pcre2")]
+            Engine::Pcre2 => {
+                let matchers = sources
+                    .iter()
+                    .map(|p| {
+                        crate::pcre2::Pcre2::new(p, crlf).map(|r| Box::new(r) as Box<dyn Matcher>)
+                    })
+                    .collect::<Result<Vec<Box<dyn Matcher>>, Errors>>()?;
+                Ok(Patterns {
+                    matchers,
+                    set: None,
+                    line_by_line: sources.iter().map(|p| anchors_text(p)).collect(),
+                })
+            }
+        }
+    }
+
+    /// ids of the patterns that match somewhere in `haystack`, along with
+    /// those that can't be checked on more than one line at once
+    pub(crate) fn matching(&self, haystack: &[u8]) -> io::Result<Vec<usize>> {
+        let set_matches = self.set.as_ref().map(|set| set.matches(haystack));
+        let mut ids: Vec<usize> = Vec::new();
+        for (id, matcher) in self.matchers.iter().enumerate() {
+            let found = match &set_matches {
+                _ if self.line_by_line[id] => true,
+                Some(set_matches) => set_matches.matched(id),
+                None => matcher.find_at(haystack, 0)?.is_some(),
+            };
+            if found {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// whether one of the `ids` has to be run on every line on its own
+    pub(crate) fn line_by_line(&self, ids: &[usize]) -> bool {
+        ids.iter().any(|&id| self.line_by_line[id])
+    }
+}
+
+/// whether `pattern` uses the start or end of the whole text, like `\A`,
+/// `\z` or `(?-m)^`, those match at each line only when every line is
+/// searched on its own, a pattern that can't be parsed here, like a PCRE2
+/// one with a back reference, is taken to
+fn anchors_text(pattern: &str) -> bool {
+    match ParserBuilder::new().multi_line(true).build().parse(pattern) {
+        Ok(hir) => {
+            let looks = hir.properties().look_set();
+            looks.contains(Look::Start) || looks.contains(Look::End)
+        }
+        Err(_) => true,
+    }
+}
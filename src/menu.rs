@@ -1,9 +1,5 @@
 // SPDX-License-Identifier: CC-BY-4.0
 
-use crate::formats;
-use crate::printer;
-use crate::searcher::Directory;
-use crate::searcher::{File, Searched};
 use crate::CONFIG;
 use crossterm::{
     cursor,
@@ -12,86 +8,79 @@ use crossterm::{
     style::{self, Print, Stylize},
     terminal::{self, ClearType},
 };
+use gret::formats;
+use gret::{File, Node, NodeKind, Searched};
 use std::ffi::OsString;
 use std::io::{self, StdoutLock, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use tempfile::TempDir;
 
 const SCROLL_OFFSET: u16 = 5;
 const START_X: u16 = 0;
 const START_Y: u16 = 0;
 
-// need to store the path and the line number?
+// the line and column start from 1, 0 when there is none
 struct Selected {
     path: OsString,
     line: usize,
+    column: usize,
+    // where a file inside of an archive was written, it is removed once
+    // the program it is opened with exits
+    extracted: Option<TempDir>,
 }
 
 impl Selected {
-    pub fn new(path: OsString, line: usize) -> Selected {
-        Selected { path, line }
-    }
-
-    fn get_selected_info(selected: usize, searched: &Searched) -> Selected {
-        let mut current: usize = 0;
-        match searched {
-            Searched::Dir(dirs) => {
-                return Selected::search_dir(dirs.get(0).unwrap(), selected, &mut current, dirs)
-                    .unwrap();
-            }
-            Searched::File(file) => {
-                return Selected::search_file(file, selected, &mut current).unwrap();
-            }
+    pub fn new(path: OsString, line: usize, column: usize) -> Selected {
+        Selected {
+            path,
+            line,
+            column,
+            extracted: None,
         }
     }
 
-    fn search_dir(
-        dir: &Directory,
-        selected: usize,
-        current: &mut usize,
-        dirs: &Vec<Directory>,
-    ) -> Option<Selected> {
-        let children = &dir.children;
-        let files = &dir.files;
-        let mut sel: Option<Selected>;
-        // this can be shortened to checking if just_files and then incrementing dir
-        if !CONFIG.just_files {
-            if *current == selected {
-                return Some(Selected::new(dir.path.clone(), 0));
-            }
-            *current += 1;
-        }
-        for child in children {
-            sel = Selected::search_dir(dirs.get(*child).unwrap(), selected, current, dirs);
-            if sel.is_some() {
-                return sel;
-            }
-        }
-        for file in files {
-            sel = Selected::search_file(file, selected, current);
-            if sel.is_some() {
-                return sel;
-            }
-        }
-        return None;
+    fn get_selected_info(selected: usize, searched: &Searched) -> io::Result<Selected> {
+        let node: Node = CONFIG.printer.nodes(searched).nth(selected).unwrap();
+        Ok(match node.kind {
+            // a directory inside of an archive opens the archive
+            NodeKind::Dir(dir) => Selected::new(
+                Path::new(&dir.path)
+                    .ancestors()
+                    .find(|p| p.exists())
+                    .map_or(dir.path.clone(), |p| p.as_os_str().to_os_string()),
+                0,
+                0,
+            ),
+            NodeKind::File(file) | NodeKind::BinaryMatches(file, _) => Selected::file(file, 0, 0)?,
+            NodeKind::Line(file, line) => Selected::file(
+                file,
+                line.line_num,
+                line.matches.first().map_or(1, |m| m.start + 1),
+            )?,
+        })
     }
 
-    fn search_file(file: &File, selected: usize, current: &mut usize) -> Option<Selected> {
-        if *current == selected {
-            return Some(Selected::new(file.path.clone().into_os_string(), 0));
-        }
-        *current += 1;
-        if !CONFIG.just_files {
-            for line in file.lines.iter() {
-                if *current == selected {
-                    return Some(Selected::new(
-                        file.path.clone().into_os_string(),
-                        line.line_num,
-                    ));
-                }
-                *current += 1;
-            }
-        }
-        None
+    /// files inside of an archive are opened from a copy written out of it
+    /// to a new temporary directory
+    fn file(file: &File, line: usize, column: usize) -> io::Result<Selected> {
+        let Some(entry) = &file.archived else {
+            return Ok(Selected::new(
+                file.path.clone().into_os_string(),
+                line,
+                column,
+            ));
+        };
+        let dir: TempDir = tempfile::Builder::new().prefix("gret-").tempdir()?;
+        let path: PathBuf = entry
+            .extract(dir.path())
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(Selected {
+            path: path.into_os_string(),
+            line,
+            column,
+            extracted: Some(dir),
+        })
     }
 }
 
@@ -107,7 +96,7 @@ pub struct Menu<'a, 'b> {
 impl<'a, 'b> Menu<'a, 'b> {
     fn new(out: &'a mut StdoutLock<'b>, searched: Searched) -> io::Result<Menu<'a, 'b>> {
         let mut buffer: Vec<u8> = Vec::new();
-        printer::write_results(&mut buffer, &searched)?;
+        CONFIG.printer.write(&mut buffer, &searched)?;
         let lines: Vec<String> = buffer
             .split(|&byte| byte == b'\n')
             .map(|vec| String::from_utf8_lossy(vec).into_owned())
@@ -145,35 +134,30 @@ impl<'a, 'b> Menu<'a, 'b> {
             {
                 match code {
                     KeyCode::Char(c) => match c {
-                        'j' => {
-                            if menu.selected_id < max_selected_id - 1 {
-                                menu.move_down()?;
-                            }
+                        'j' if menu.selected_id < max_selected_id - 1 => {
+                            menu.move_down()?;
                         }
-                        'k' => {
-                            if menu.selected_id > 0 {
-                                menu.move_up()?;
-                            }
+                        'k' if menu.selected_id > 0 => {
+                            menu.move_up()?;
                         }
                         'q' => break 'outer,
-                        'c' => {
-                            if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) {
-                                break 'outer;
-                            }
+                        'c' if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                            break 'outer;
                         }
-                        'z' => {
-                            if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) {
-                                menu.suspend()?;
-                                menu.resume()?;
-                            }
+                        'z' if modifiers.contains(crossterm::event::KeyModifiers::CONTROL) => {
+                            menu.suspend()?;
+                            menu.resume()?;
                         }
                         _ => {}
                     },
                     KeyCode::Enter => {
-                        return menu.exit_and_open(Selected::get_selected_info(
-                            menu.selected_id,
-                            &menu.searched,
-                        ));
+                        return match Selected::get_selected_info(menu.selected_id, &menu.searched) {
+                            Ok(selected) => menu.exit_and_open(selected),
+                            Err(e) => {
+                                menu.leave()?;
+                                Err(e)
+                            }
+                        };
                     }
                     _ => {}
                 }
@@ -322,12 +306,17 @@ impl<'a, 'b> Menu<'a, 'b> {
 
     #[cfg(windows)]
     fn exit_and_open(&mut self, selected: Selected) -> io::Result<()> {
-        Command::new("cmd")
-            .arg("/C")
-            .arg("start")
-            .arg(selected.path)
-            .spawn()?;
-        self.leave()
+        let mut command: Command = Command::new("cmd");
+        command.arg("/C").arg("start");
+        if selected.extracted.is_none() {
+            command.arg(selected.path).spawn()?;
+            return self.leave();
+        }
+        // wait for the program so the copy can be removed after it
+        command.arg("/wait").arg(selected.path);
+        self.leave()?;
+        command.status()?;
+        Ok(())
     }
 
     #[cfg(not(windows))]
@@ -341,18 +330,38 @@ impl<'a, 'b> Menu<'a, 'b> {
         };
 
         let line_num: usize = selected.line;
+        let column: usize = selected.column.max(1);
         let mut command: Command = Command::new(&opener);
         match opener.as_str() {
-            "vi" | "vim" | "nvim" | "nano" | "emacs" => {
-                command.arg(format!("+{line_num}"));
+            "vi" | "vim" | "nvim" => {
+                command.arg(format!("+call cursor({line_num}, {column})"));
+                command.arg(selected.path);
+            }
+            "nano" => {
+                command.arg(format!("+{line_num},{column}"));
+                command.arg(selected.path);
+            }
+            "emacs" => {
+                command.arg(format!("+{line_num}:{column}"));
                 command.arg(selected.path);
             }
             "hx" => {
-                command.arg(format!("{}:{line_num}", selected.path.to_string_lossy()));
+                command.arg(format!(
+                    "{}:{line_num}:{column}",
+                    selected.path.to_string_lossy()
+                ));
             }
             "code" => {
                 command.arg("--goto");
-                command.arg(format!("{}:{line_num}", selected.path.to_string_lossy()));
+                command.arg(format!(
+                    "{}:{line_num}:{column}",
+                    selected.path.to_string_lossy()
+                ));
+            }
+            "open" if selected.extracted.is_some() => {
+                // wait for the program so the copy can be removed after it
+                command.arg("-W");
+                command.arg(selected.path);
             }
             _ => {
                 command.arg(selected.path);
@@ -361,7 +370,15 @@ impl<'a, 'b> Menu<'a, 'b> {
         use std::os::unix::process::CommandExt;
         self.leave()?;
 
-        command.exec();
+        let Some(dir) = selected.extracted else {
+            return Err(command.exec());
+        };
+        command.status()?;
+        // xdg-open gives the file to another program and returns without
+        // waiting for it, so the copy is left for that program
+        if opener == "xdg-open" {
+            let _ = dir.keep();
+        }
         Ok(())
     }
 }
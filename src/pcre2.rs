@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+// -P/--pcre2 patterns, matched by PCRE2 through the pcre2 crate
+
+use crate::errors::Errors;
+use crate::matcher::Matcher;
+use ::pcre2::bytes::{Regex, RegexBuilder};
+use std::io;
+
+pub(crate) struct Pcre2 {
+    regex: Regex,
+}
+
+impl Pcre2 {
+    pub(crate) fn new(pattern: &str, crlf: bool) -> Result<Pcre2, Errors> {
+        // UTF mode also lets bytes that aren't valid UTF-8 be searched,
+        // they just can't be part of a match
+        let regex: Regex = RegexBuilder::new()
+            .utf(true)
+            .ucp(true)
+            .multi_line(true)
+            .crlf(crlf)
+            // matching still works without the jit, just slower
+            .jit_if_available(true)
+            .build(pattern)
+            .map_err(|e| Errors::InvalidRegex {
+                cause: format!("{}, {}", pattern, e),
+            })?;
+        Ok(Pcre2 { regex })
+    }
+
+    fn group_number(&self, name: &str) -> Option<usize> {
+        if let Ok(number) = name.parse::<usize>() {
+            return Some(number);
+        }
+        self.regex
+            .capture_names()
+            .iter()
+            .position(|group| group.as_deref() == Some(name))
+    }
+}
+
+/// errors like hitting the match limit are given back
+fn match_error(e: ::pcre2::Error) -> io::Error {
+    io::Error::other(format!("PCRE2 failed to match, {}", e))
+}
+
+impl Matcher for Pcre2 {
+    fn find_at(&self, haystack: &[u8], start: usize) -> io::Result<Option<(usize, usize)>> {
+        if start > haystack.len() {
+            return Ok(None);
+        }
+        let found = self.regex.find_at(haystack, start).map_err(match_error)?;
+        // `\K` in a look ahead can put the start after the end
+        Ok(found.map(|m| (m.start().min(m.end()), m.end())))
+    }
+
+    fn expand_at(
+        &self,
+        haystack: &[u8],
+        start: usize,
+        template: &str,
+        dst: &mut Vec<u8>,
+    ) -> io::Result<()> {
+        if start > haystack.len() {
+            return Ok(());
+        }
+        let mut locations = self.regex.capture_locations();
+        let found = self
+            .regex
+            .captures_read_at(&mut locations, haystack, start)
+            .map_err(match_error)?;
+        if found.is_some() {
+            expand(template, dst, |name| {
+                let (start, end) = locations.get(self.group_number(name)?)?;
+                haystack.get(start..end)
+            });
+        }
+        Ok(())
+    }
+}
+
+/// the same syntax as the regex crate's expansion, `$name` takes the
+/// longest run of letters, digits and `_`, a missing group is empty
+fn expand<'h>(template: &str, dst: &mut Vec<u8>, group: impl Fn(&str) -> Option<&'h [u8]>) {
+    let is_name = |c: char| c == '_' || c.is_ascii_alphanumeric();
+    let mut rest: &str = template;
+    while let Some(i) = rest.find('$') {
+        dst.extend_from_slice(&rest.as_bytes()[..i]);
+        rest = &rest[i + 1..];
+        let (name, after) = if let Some(braced) = rest.strip_prefix('{') {
+            match braced.find('}') {
+                Some(close) => (&braced[..close], &braced[close + 1..]),
+                None => ("", rest),
+            }
+        } else if let Some(after) = rest.strip_prefix('$') {
+            dst.push(b'$');
+            rest = after;
+            continue;
+        } else {
+            let len = rest.find(|c: char| !is_name(c)).unwrap_or(rest.len());
+            (&rest[..len], &rest[len..])
+        };
+        if name.is_empty() {
+            dst.push(b'$');
+        } else if let Some(text) = group(name) {
+            dst.extend_from_slice(text);
+        }
+        rest = after;
+    }
+    dst.extend_from_slice(rest.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_all(pattern: &str, haystack: &str) -> Vec<(usize, usize)> {
+        Pcre2::new(pattern, false)
+            .unwrap()
+            .find_iter(haystack.as_bytes())
+            .unwrap()
+    }
+
+    #[test]
+    fn back_reference() {
+        assert_eq!(find_all(r"(\w+)\s+\1", "it is is here"), vec![(3, 8)]);
+        assert_eq!(find_all(r"(\w+)\s+\1\b", "the theory"), vec![]);
+    }
+
+    #[test]
+    fn negative_look_ahead() {
+        assert_eq!(find_all("foo(?!bar)", "foobar foobaz"), vec![(7, 10)]);
+    }
+
+    #[test]
+    fn unicode_words_and_dots() {
+        // `é` is two bytes, both are part of the word and of the one `.`
+        assert_eq!(find_all(r"\w+", "café au"), vec![(0, 5), (6, 8)]);
+        assert_eq!(find_all("f.", "fé"), vec![(0, 3)]);
+        assert_eq!(find_all(r"(\w+)\s+\1", "été été"), vec![(0, 11)]);
+    }
+
+    #[test]
+    fn expand_keeps_whole_characters() {
+        let pcre2 = Pcre2::new("(.)x", false).unwrap();
+        let mut dst: Vec<u8> = Vec::new();
+        pcre2
+            .expand_at("éx".as_bytes(), 0, "[$1]", &mut dst)
+            .unwrap();
+        assert_eq!(dst, "[é]".as_bytes());
+    }
+
+    #[test]
+    fn empty_matches_step_over_characters() {
+        assert_eq!(find_all("", "é"), vec![(0, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn invalid_utf8_is_searched() {
+        let pcre2 = Pcre2::new("b.", false).unwrap();
+        assert_eq!(
+            pcre2.find_iter(b"a\xFF\xFEb\xC3\xA9").unwrap(),
+            vec![(3, 6)]
+        );
+    }
+
+    #[test]
+    fn match_limit_is_an_error() {
+        let pcre2 = Pcre2::new("(*LIMIT_MATCH=10)(a+)+$", false).unwrap();
+        assert!(pcre2.find_at(b"aaaaaaaaaaaaaaaaaaaab", 0).is_err());
+    }
+}
@@ -1,217 +1,564 @@
 // SPDX-License-Identifier: CC-BY-4.0
 
-use crate::formats::{self, BRANCH_END, BRANCH_HAS_NEXT, SPACER, VER_LINE_SPACER};
-use crate::searcher::{Directory, File, MatchedLine, Searched};
-use crate::CONFIG;
+use crate::formats::{self, BRANCH_END, BRANCH_HAS_NEXT, ELLIPSIS, SPACER, VER_LINE_SPACER};
+use crate::searcher::{self, Directory, File, Match, MatchedLine, Searched, Segment};
+use crate::tree::{NodeKind, Nodes};
+use crossterm::style::StyledContent;
+use std::borrow::Cow;
+use std::fmt::Write as _;
 use std::io::{self, Write};
 
-fn write_file_path(out: &mut impl Write, file: &File) -> io::Result<()> {
-    let path: &str = &file.path.to_string_lossy();
-    if let Some(linked) = &file.linked {
-        if CONFIG.styled {
-            write!(out, "{} -> ", formats::file_name(path))?;
-        } else {
-            write!(out, "{} -> ", path)?
+/// a preview of a long line shows at most this many times `max_columns`
+/// bytes, a line with more around its matches is left out
+const MAX_PREVIEW_WIDTHS: usize = 4;
+
+/// Writes a [`Searched`] as a tree to any writer, built with
+/// [`TreePrinter::new`] and the chained setters.
+pub struct TreePrinter {
+    styled: bool,
+    show_count: bool,
+    show_line_number: bool,
+    just_files: bool,
+    trim: bool,
+    vimgrep: bool,
+    summary: bool,
+    max_columns: Option<usize>,
+    max_columns_preview: bool,
+    terminator: String,
+    reset: String,
+}
+
+impl Default for TreePrinter {
+    fn default() -> Self {
+        TreePrinter::new()
+    }
+}
+
+impl TreePrinter {
+    pub fn new() -> TreePrinter {
+        TreePrinter {
+            styled: false,
+            show_count: false,
+            show_line_number: false,
+            just_files: false,
+            trim: false,
+            vimgrep: false,
+            summary: false,
+            max_columns: None,
+            max_columns_preview: false,
+            terminator: formats::get_terminator(false),
+            reset: formats::get_reset(false),
         }
-        if CONFIG.styled {
-            write!(out, "{}", formats::file_name(&linked.to_string_lossy()))?;
-        } else {
-            write!(out, "{}", linked.to_string_lossy())?;
+    }
+
+    pub fn styled(mut self, styled: bool) -> TreePrinter {
+        self.styled = styled;
+        self
+    }
+
+    pub fn show_count(mut self, show_count: bool) -> TreePrinter {
+        self.show_count = show_count;
+        self
+    }
+
+    pub fn line_number(mut self, show_line_number: bool) -> TreePrinter {
+        self.show_line_number = show_line_number;
+        self
+    }
+
+    pub fn just_files(mut self, just_files: bool) -> TreePrinter {
+        self.just_files = just_files;
+        self
+    }
+
+    /// strip the leading and trailing spaces and tabs of shown lines
+    pub fn trim(mut self, trim: bool) -> TreePrinter {
+        self.trim = trim;
+        self
+    }
+
+    /// lines longer than this many bytes are replaced by a note of how many
+    /// matches they had
+    pub fn max_columns(mut self, max_columns: Option<usize>) -> TreePrinter {
+        self.max_columns = max_columns;
+        self
+    }
+
+    /// instead of leaving out lines longer than `max_columns` show a
+    /// window of that many bytes around each match
+    pub fn max_columns_preview(mut self, max_columns_preview: bool) -> TreePrinter {
+        self.max_columns_preview = max_columns_preview;
+        self
+    }
+
+    /// write each match as `path:line:column:text` instead of a tree
+    pub fn vimgrep(mut self, vimgrep: bool) -> TreePrinter {
+        self.vimgrep = vimgrep;
+        self
+    }
+
+    /// leave out the matched lines and only write directories and files
+    pub fn summary(mut self, summary: bool) -> TreePrinter {
+        self.summary = summary;
+        self
+    }
+
+    /// use the line endings and resets needed when drawing in the raw mode menu
+    pub fn menu(mut self, menu: bool) -> TreePrinter {
+        self.terminator = formats::get_terminator(menu);
+        self.reset = formats::get_reset(menu);
+        self
+    }
+
+    /// the rows this printer writes, in the same order
+    pub fn nodes<'a>(&self, searched: &'a Searched) -> Nodes<'a> {
+        searched.nodes(self.just_files)
+    }
+
+    pub fn write(&self, out: &mut impl Write, searched: &Searched) -> io::Result<()> {
+        if self.vimgrep {
+            for node in self.nodes(searched) {
+                if let NodeKind::Line(file, line) = node.kind {
+                    self.write_vimgrep(out, file, line)?;
+                }
+            }
+            return Ok(());
         }
-    } else {
-        if CONFIG.styled {
-            write!(out, "{}", formats::file_name(path))?;
-        } else {
-            write!(out, "{}", path)?;
+        // for each depth, whether the node last seen there was the last of its siblings
+        let mut last_at_depth: Vec<bool> = Vec::new();
+        for node in self.nodes(searched) {
+            if self.summary && matches!(node.kind, NodeKind::Line(..) | NodeKind::BinaryMatches(..))
+            {
+                continue;
+            }
+            last_at_depth.truncate(node.depth);
+            if node.depth > 0 {
+                for is_last in last_at_depth.iter().skip(1) {
+                    write!(out, "{}", if *is_last { SPACER } else { VER_LINE_SPACER })?;
+                }
+                write!(
+                    out,
+                    "{}",
+                    if node.is_last {
+                        BRANCH_END
+                    } else {
+                        BRANCH_HAS_NEXT
+                    }
+                )?;
+            }
+            last_at_depth.push(node.is_last);
+            match node.kind {
+                NodeKind::Dir(dir) => self.write_dir_name(out, dir)?,
+                NodeKind::File(file) => {
+                    if self.just_files {
+                        self.write_file_path(out, file)?;
+                    } else {
+                        self.write_file_name(out, file)?;
+                    }
+                }
+                NodeKind::Line(file, line) => {
+                    self.print_line(out, line, file.binary == Some(true))?;
+                    self.new_line(out)?;
+                }
+                NodeKind::BinaryMatches(_, count) => {
+                    write!(out, "binary file matches ({})", count)?;
+                    self.new_line(out)?;
+                }
+            }
         }
+
+        Ok(())
     }
-    if CONFIG.show_count {
-        write!(out, ": {}", file.lines.len())?;
-    }
-    new_line(out)?;
 
-    Ok(())
-}
+    fn write_file_path(&self, out: &mut impl Write, file: &File) -> io::Result<()> {
+        let path: &str = &file.path.to_string_lossy();
+        // the name is at the end of the path, only it has matches
+        match path.strip_suffix(file.name.as_str()) {
+            Some(parents) => {
+                self.write_name(out, parents, &[], formats::file_name)?;
+                self.write_name(out, &file.name, &file.name_matches, formats::file_name)?;
+            }
+            None => self.write_name(out, path, &[], formats::file_name)?,
+        }
+        if let Some(linked) = &file.linked {
+            write!(out, " -> ")?;
+            self.write_name(out, &linked.to_string_lossy(), &[], formats::file_name)?;
+        }
+        // a file matched only by its name has no lines to count
+        if self.show_count && !file.lines.is_empty() {
+            write!(out, ": {}", file.lines.len())?;
+        }
+        self.new_line(out)?;
 
-pub fn write_results(out: &mut impl Write, result: &Searched) -> io::Result<()> {
-    let prefix = "".into();
-    match &result {
-        Searched::Dir(dirs) => {
-            write_dir(out, dirs.get(0).unwrap(), prefix, dirs)?;
+        Ok(())
+    }
+
+    fn write_file_name(&self, out: &mut impl Write, file: &File) -> io::Result<()> {
+        self.write_name(out, &file.name, &file.name_matches, formats::file_name)?;
+        if let Some(linked) = &file.linked {
+            write!(out, " -> ")?;
+            self.write_name(out, &linked.to_string_lossy(), &[], formats::file_name)?;
         }
-        Searched::File(file) => {
-            write_file(out, &file, prefix, false)?;
+        // a file matched only by its name has no lines to count
+        if self.show_count && !file.lines.is_empty() {
+            write!(out, ": {}", file.lines.len())?;
         }
+        self.new_line(out)?;
+
+        Ok(())
     }
 
-    Ok(())
-}
+    fn write_dir_name(&self, out: &mut impl Write, dir: &Directory) -> io::Result<()> {
+        self.write_name(out, &dir.name, &dir.name_matches, formats::dir_name)?;
+        if let Some(linked) = &dir.linked {
+            write!(out, " -> ")?;
+            self.write_name(out, &linked.to_string_lossy(), &[], formats::dir_name)?;
+        }
+        if self.show_count {
+            write!(out, ": {}", dir.files.len() + dir.children.len())?;
+        }
+        self.new_line(out)?;
+        Ok(())
+    }
 
-fn write_dir(
-    out: &mut impl Write,
-    dir: &Directory,
-    prefix: String,
-    dirs: &Vec<Directory>,
-) -> io::Result<()> {
-    let children = &dir.children;
-    let files = &dir.files;
-    let flen = files.len();
-    let clen = children.len();
-    if (clen > 0 || flen > 0) && !CONFIG.just_files {
-        write_dir_name(out, &dir)?;
-    }
-    let mut i: usize = 0;
-    for child_id in children {
-        i += 1;
-        // check if it has a next file
-        let dir = dirs.get(*child_id).unwrap();
-        if i != clen || flen > 0 {
-            if !CONFIG.just_files {
-                write!(out, "{}{}", prefix, BRANCH_HAS_NEXT)?;
-            }
-            write_dir(out, dir, (prefix.clone() + VER_LINE_SPACER).clone(), dirs)?;
-        } else {
-            if !CONFIG.just_files {
-                write!(out, "{}{}", prefix, BRANCH_END)?;
+    /// write a file or directory name in its `style` with the matched
+    /// parts in the colours of their patterns
+    fn write_name(
+        &self,
+        out: &mut impl Write,
+        name: &str,
+        name_matches: &[Match],
+        style: fn(&str) -> StyledContent<&str>,
+    ) -> io::Result<()> {
+        if !self.styled {
+            return write!(out, "{}", name);
+        }
+        let bytes: &[u8] = name.as_bytes();
+        let mut last_match: usize = 0;
+        for segment in searcher::segments(name_matches) {
+            if segment.start > last_match {
+                let before = String::from_utf8_lossy(&bytes[last_match..segment.start]);
+                write!(out, "{}", style(&before))?;
             }
-            write_dir(out, dir, (prefix.clone() + SPACER).clone(), dirs)?;
+            write!(
+                out,
+                "{}{}{}",
+                formats::get_segment_color(&segment.regex_ids),
+                formats::BOLD,
+                String::from_utf8_lossy(&bytes[segment.start..segment.end])
+            )?;
+            self.write_resets(out)?;
+            last_match = segment.end;
         }
+        if last_match < bytes.len() {
+            write!(
+                out,
+                "{}",
+                style(&String::from_utf8_lossy(&bytes[last_match..]))
+            )?;
+        }
+        Ok(())
     }
-    i = 0;
-    for file in files {
-        i += 1;
-        // check if it has a next file
-        if i != flen {
-            write_file(out, file, prefix.clone(), true)?;
-        } else {
-            write_file(out, file, prefix.clone(), false)?;
+
+    fn print_line(
+        &self,
+        out: &mut impl Write,
+        line_match: &MatchedLine,
+        escape: bool,
+    ) -> io::Result<()> {
+        if self.show_line_number {
+            if self.styled {
+                write!(out, "{}{}", formats::LINE_NUMBER_FG, formats::BOLD)?;
+            }
+            write!(out, "{}: ", line_match.line_num)?;
+            if self.styled {
+                self.write_resets(out)?;
+            }
         }
+        self.write_text(out, line_match, escape)
     }
-    Ok(())
-}
 
-fn write_file(
-    out: &mut impl Write,
-    file: &File,
-    mut prefix: String,
-    parent_has_next: bool,
-) -> io::Result<()> {
-    if CONFIG.just_files {
-        return write_file_path(out, file);
-    }
-    let len = file.lines.len();
-    if prefix == "" {
-        write_file_name(out, &file)?;
-    } else if parent_has_next {
-        write!(out, "{}{}", prefix, BRANCH_HAS_NEXT)?;
-        write_file_name(out, &file)?;
-        prefix += VER_LINE_SPACER;
-    } else {
-        write!(out, "{}{}", prefix, BRANCH_END)?;
-        write_file_name(out, &file)?;
-        prefix += SPACER;
-    }
-
-    let mut i = 0;
-    for line_match in file.lines.iter() {
-        i += 1;
-        if i != len {
-            write!(out, "{}{}", prefix, BRANCH_HAS_NEXT,)?;
+    /// write the text of a line, when `escape` is set unprintable bytes
+    /// are written as `\xNN` so binary files can't mess up the terminal
+    fn write_text(
+        &self,
+        out: &mut impl Write,
+        line_match: &MatchedLine,
+        escape: bool,
+    ) -> io::Result<()> {
+        let replaced: MatchedLine;
+        let line_match: &MatchedLine = if line_match.matches.iter().any(|m| m.replacement.is_some())
+        {
+            replaced = line_match.replaced();
+            &replaced
         } else {
-            write!(out, "{}{}", prefix, BRANCH_END)?;
+            line_match
+        };
+        let line: &[u8] = &line_match.contents;
+        let (start, end) = if self.trim {
+            line.trimmed_bounds()
+        } else {
+            (0, line.len())
+        };
+        let segments: Vec<Segment> = line_match.segments();
+        match self.max_columns {
+            Some(max) if end - start > max => {
+                let windows = if self.max_columns_preview {
+                    match_windows(line_match, start, end, max)
+                } else {
+                    Vec::new()
+                };
+                let shown: usize = windows.iter().map(|(start, end)| end - start).sum();
+                if windows.is_empty() || shown > max.saturating_mul(MAX_PREVIEW_WIDTHS) {
+                    return write!(
+                        out,
+                        "[omitted long line with {} matches]",
+                        line_match.matches.len()
+                    );
+                }
+                let mut last_end = start;
+                for (window_start, window_end) in windows {
+                    if window_start > last_end {
+                        write!(out, "{}", ELLIPSIS)?;
+                    }
+                    self.write_range(out, line, &segments, window_start, window_end, escape)?;
+                    last_end = window_end;
+                }
+                if last_end < end {
+                    write!(out, "{}", ELLIPSIS)?;
+                }
+                Ok(())
+            }
+            _ => self.write_range(out, line, &segments, start, end, escape),
         }
-        print_line(out, line_match)?;
-        new_line(out)?;
     }
 
-    Ok(())
-}
-
-pub fn write_file_name(out: &mut impl Write, file: &File) -> io::Result<()> {
-    if let Some(linked) = &file.linked {
-        if CONFIG.styled {
-            write!(out, "{} -> ", formats::file_name(&file.name))?;
-        } else {
-            write!(out, "{} -> ", file.name)?
+    /// write `line[start..end]` with the parts of the segments inside of it styled
+    fn write_range(
+        &self,
+        out: &mut impl Write,
+        line: &[u8],
+        segments: &[Segment],
+        start: usize,
+        end: usize,
+        escape: bool,
+    ) -> io::Result<()> {
+        if !self.styled {
+            write!(out, "{}", text(&line[start..end], escape))?;
+            return Ok(());
         }
-        if CONFIG.styled {
-            write!(out, "{}", formats::file_name(&linked.to_string_lossy()))?;
-        } else {
-            write!(out, "{}", linked.to_string_lossy())?;
+        let mut last_match = start;
+        for segment in segments {
+            // segments are cut to what is shown
+            let seg_start = segment.start.clamp(last_match, end);
+            let seg_end = segment.end.clamp(seg_start, end);
+            if seg_start == seg_end {
+                continue;
+            }
+            write!(out, "{}", text(&line[last_match..seg_start], escape))?;
+            last_match = seg_end;
+            write!(
+                out,
+                "{}{}",
+                formats::get_segment_color(&segment.regex_ids),
+                formats::BOLD,
+            )?;
+            write!(out, "{}", text(&line[seg_start..seg_end], escape))?;
+            self.write_resets(out)?;
         }
-    } else {
-        if CONFIG.styled {
-            write!(out, "{}", formats::file_name(&file.name))?;
-        } else {
-            write!(out, "{}", file.name)?;
+        write!(out, "{}", text(&line[last_match..end], escape))?;
+
+        Ok(())
+    }
+
+    /// a line for each match as `path:line:column:text`, with the column
+    /// counted in bytes from 1
+    fn write_vimgrep(
+        &self,
+        out: &mut impl Write,
+        file: &File,
+        line: &MatchedLine,
+    ) -> io::Result<()> {
+        for m in line.matches.iter() {
+            write!(
+                out,
+                "{}:{}:{}:",
+                file.path.to_string_lossy(),
+                line.line_num,
+                m.start + 1
+            )?;
+            self.write_text(out, line, file.binary == Some(true))?;
+            self.new_line(out)?;
         }
+        Ok(())
     }
-    if CONFIG.show_count {
-        write!(out, ": {}", file.lines.len())?;
+
+    fn write_resets(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "{}", self.reset)
     }
-    new_line(out)?;
 
-    Ok(())
+    fn new_line(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "{}", self.terminator)
+    }
 }
 
-fn write_dir_name(out: &mut impl Write, dir: &Directory) -> io::Result<()> {
-    if CONFIG.styled {
-        write!(out, "{}", formats::dir_name(&dir.name))?;
-    } else {
-        write!(out, "{}", dir.name)?;
+/// the bytes as text, escaping control characters other than tab and
+/// bytes that aren't UTF-8 when `escape` is set
+fn text(bytes: &[u8], escape: bool) -> Cow<'_, str> {
+    if !escape {
+        return String::from_utf8_lossy(bytes);
     }
-    if CONFIG.show_count {
-        write!(out, ": {}", dir.files.len() + dir.children.len())?;
+    let mut escaped = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c.is_control() && c != '\t' {
+                for b in c.encode_utf8(&mut [0; 4]).bytes() {
+                    let _ = write!(escaped, "\\x{:02X}", b);
+                }
+            } else {
+                escaped.push(c);
+            }
+        }
+        for b in chunk.invalid() {
+            let _ = write!(escaped, "\\x{:02X}", b);
+        }
     }
-    new_line(out)?;
-    Ok(())
+    Cow::Owned(escaped)
 }
 
-pub fn print_line(out: &mut impl Write, line_match: &MatchedLine) -> std::io::Result<()> {
+/// ranges of at most `max` bytes centered on each match, ranges that
+/// touch are joined and none go outside of `start..end`
+fn match_windows(
+    line_match: &MatchedLine,
+    start: usize,
+    end: usize,
+    max: usize,
+) -> Vec<(usize, usize)> {
     let line: &[u8] = &line_match.contents;
-    // let line: &[u8] = &line_match.contents;
-    let line_num = line_match.line_num;
-    if !CONFIG.styled {
-        if CONFIG.show_line_number {
-            write!(out, "{}: ", line_num)?;
+    let mut windows: Vec<(usize, usize)> = Vec::new();
+    for m in line_match.matches.iter() {
+        let m_start = m.start.clamp(start, end);
+        let pad = max.saturating_sub(m.end - m.start) / 2;
+        let window_end = (m_start.saturating_sub(pad).max(start) + max).min(end);
+        let window_start = window_end.saturating_sub(max).max(start);
+        let window_end = line.char_boundary_before(window_end);
+        // a window inside of one character would otherwise end before it
+        // starts, there is nothing in it to show
+        let window_start = line.char_boundary_after(window_start).min(window_end);
+        if window_start == window_end {
+            continue;
+        }
+        match windows.last_mut() {
+            Some(last) if window_start <= last.1 => last.1 = last.1.max(window_end),
+            _ => windows.push((window_start, window_end)),
         }
-        write!(out, "{}", String::from_utf8_lossy(&line).trim())?;
-        return Ok(());
     }
-    let mut last_match = 0;
-    if CONFIG.show_line_number {
-        if CONFIG.styled {
-            write!(out, "{}{}", formats::LINE_NUMBER_FG, formats::BOLD)?;
+    windows
+}
+
+trait SliceExt {
+    fn trimmed_bounds(&self) -> (usize, usize);
+    fn char_boundary_after(&self, i: usize) -> usize;
+    fn char_boundary_before(&self, i: usize) -> usize;
+}
+
+impl SliceExt for [u8] {
+    /// start and end of the slice without leading or trailing tabs and spaces
+    fn trimmed_bounds(&self) -> (usize, usize) {
+        fn is_not_whitespace(c: &u8) -> bool {
+            *c != b'\t' && *c != b' '
         }
-        write!(out, "{}: ", line_num)?;
-        if CONFIG.styled {
-            write_resets(out)?;
+
+        match self.iter().position(is_not_whitespace) {
+            Some(first) => (first, self.iter().rposition(is_not_whitespace).unwrap() + 1),
+            None => (0, 0),
         }
     }
-    for m in line_match.matches.iter() {
-        write!(
-            out,
-            "{}",
-            String::from_utf8_lossy(&line[last_match..m.start])
-        )?;
-        last_match = m.end;
-        if CONFIG.styled {
-            write!(out, "{}{}", formats::get_color(m.regex_id), formats::BOLD,)?;
-        }
-        write!(out, "{}", String::from_utf8_lossy(&line[m.start..m.end]))?;
-        if CONFIG.styled {
-            write_resets(out)?;
+
+    /// move `i` forward until it is not inside of a utf-8 character
+    fn char_boundary_after(&self, mut i: usize) -> usize {
+        while i < self.len() && (self[i] & 0b1100_0000) == 0b1000_0000 {
+            i += 1;
         }
+        i
     }
-    write!(out, "{}", String::from_utf8_lossy(&line[last_match..]))?;
 
-    Ok(())
+    /// move `i` back until it is not inside of a utf-8 character
+    fn char_boundary_before(&self, mut i: usize) -> usize {
+        while i > 0 && i < self.len() && (self[i] & 0b1100_0000) == 0b1000_0000 {
+            i -= 1;
+        }
+        i
+    }
 }
 
-fn write_resets(out: &mut impl Write) -> io::Result<()> {
-    write!(out, "{}", CONFIG.reset)
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched_line(contents: &str, spans: &[(usize, usize)]) -> MatchedLine {
+        MatchedLine {
+            line_num: 1,
+            contents: contents.as_bytes().to_vec(),
+            matches: spans
+                .iter()
+                .map(|&(start, end)| Match {
+                    regex_id: 0,
+                    start,
+                    end,
+                    replacement: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn windows_inside_of_a_character() {
+        // each match is the second byte of a three byte `€`
+        let line = matched_line(&"€".repeat(20), &[(1, 2), (4, 5), (31, 32)]);
+        assert_eq!(match_windows(&line, 0, 60, 1), vec![]);
+        assert_eq!(match_windows(&line, 0, 60, 3), vec![(0, 6), (30, 33)]);
+    }
+    #[test]
+    fn windows_are_centered_and_joined() {
+        let line = matched_line(&"a".repeat(100), &[(10, 13), (14, 17), (80, 83)]);
+        assert_eq!(match_windows(&line, 0, 100, 10), vec![(7, 21), (77, 87)]);
+    }
+
+    #[test]
+    fn windows_stay_inside_the_shown_text() {
+        let line = matched_line(&"a".repeat(60), &[(6, 7), (48, 50)]);
+        assert_eq!(match_windows(&line, 5, 50, 10), vec![(5, 15), (40, 50)]);
+    }
 
-fn new_line(out: &mut impl Write) -> io::Result<()> {
-    write!(out, "{}", CONFIG.terminator)
+    fn preview(contents: &str, spans: &[(usize, usize)], max: usize) -> String {
+        let printer = TreePrinter::new()
+            .max_columns(Some(max))
+            .max_columns_preview(true);
+        let mut out: Vec<u8> = Vec::new();
+        printer
+            .write_text(&mut out, &matched_line(contents, spans), false)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn previews_that_are_too_long_are_omitted() {
+        let contents: String = format!("{}foo{}", "x".repeat(50), "x".repeat(50));
+        assert_eq!(
+            preview(&contents, &[(50, 53)], 9),
+            format!("{}xxxfooxxx{}", ELLIPSIS, ELLIPSIS)
+        );
+        let contents: String = ("x".repeat(20) + "foo").repeat(10);
+        let spans: Vec<(usize, usize)> = (0..10).map(|i| (i * 23 + 20, i * 23 + 23)).collect();
+        assert_eq!(
+            preview(&contents, &spans, 9),
+            "[omitted long line with 10 matches]"
+        );
+        // a match inside of a character leaves nothing to show
+        assert_eq!(
+            preview(&"€".repeat(20), &[(1, 2)], 1),
+            "[omitted long line with 1 matches]"
+        );
+    }
 }
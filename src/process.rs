@@ -0,0 +1,61 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+use std::fs;
+use std::io::{self, Read};
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+/// The output of a program that reads a file on its stdin, like a
+/// decompressor or a preprocessor. Reading it fails at the end if the
+/// program did.
+pub(crate) struct CommandOutput {
+    child: Child,
+    stdout: ChildStdout,
+    program: String,
+}
+
+impl CommandOutput {
+    pub(crate) fn spawn(
+        mut command: Command,
+        file: fs::File,
+        program: String,
+    ) -> io::Result<CommandOutput> {
+        let mut child: Child = command
+            .stdin(file)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("failed to run {}: {}", program, e)))?;
+        let stdout: ChildStdout = child.stdout.take().expect("stdout is piped");
+        Ok(CommandOutput {
+            child,
+            stdout,
+            program,
+        })
+    }
+}
+
+impl Read for CommandOutput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stdout.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!(
+                    "{} failed with {}",
+                    self.program, status
+                )));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for CommandOutput {
+    // a file can stop being read before the end, like a skipped binary file
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
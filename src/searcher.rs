@@ -1,117 +1,740 @@
 // SPDX-License-Identifier: CC-BY-4.0
 
-use crate::Errors;
-use crate::CONFIG;
-use ignore::WalkBuilder;
-use memchr::memchr;
+use crate::archive::{archive_kind, read_archive, ArchiveEntry, ArchiveKind, Contents};
+use crate::decompress::decompressor;
+use crate::encoding::{DecodeReader, Encoding};
+use crate::errors::Errors;
+use crate::explain::{walk_error, SkipReason, Skipped};
+use crate::lines::LineBuffer;
+use crate::matcher::{Engine, Patterns};
+use crate::process::CommandOutput;
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::{DirEntry, WalkBuilder};
+use memchr::{memchr, memchr_iter, memrchr};
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::SystemTime;
 
+/// how much of the start of a file is looked at to decide if it is binary
+pub(crate) const BINARY_PREFIX_LEN: usize = 8 * 1024;
+/// the percent of odd bytes in that prefix that makes a file binary
+const BINARY_PERCENT: usize = 10;
+
+/// ignore files with this name are used like `.ignore` files
+pub(crate) const IGNORE_FILE_NAME: &str = ".gretignore";
+/// directories of version control systems, skipped unless searching everything
+pub(crate) const VCS_DIRS: [&str; 3] = [".git", ".hg", ".svn"];
+
+/// A directory in the tree, children are indexes into the arena
+/// held by [`Searched::Dir`], the root is always at index 0.
 pub struct Directory {
     pub name: String,
     pub children: Vec<usize>,
     pub files: Vec<File>,
     pub path: OsString,
+    /// matches in `name` when searching paths
+    pub name_matches: Vec<Match>,
+    pub linked: Option<PathBuf>,
     to_add: bool,
 }
 
 pub struct File {
     pub name: String,
     pub lines: Vec<MatchedLine>,
+    /// matches in `name` when searching paths
+    pub name_matches: Vec<Match>,
     pub linked: Option<PathBuf>,
     pub path: PathBuf,
+    /// whether the start of the file looked binary, `None` if it wasn't read
+    pub binary: Option<bool>,
+    /// the number of matches in a binary file when they are only reported
+    pub binary_matches: Option<usize>,
+    /// the encoding the file was decoded from, `None` if it was read as it is
+    pub encoding: Option<Encoding>,
+    // whether the file starts with a byte order mark
+    pub(crate) bom: bool,
+    /// whether the file was decompressed before it was searched
+    pub compressed: bool,
+    /// whether the output of the preprocessor was searched instead of the file
+    pub preprocessed: bool,
+    /// the archive the file was read from, its `path` is the archive's
+    /// path joined with its path inside of it
+    pub archived: Option<ArchiveEntry>,
+    // when it was modified and its length as it was searched
+    pub(crate) read_stamp: Option<(SystemTime, u64)>,
 }
 
 pub enum Searched {
     Dir(Vec<Directory>),
     File(File),
+    Many(Vec<Searched>),
 }
 
 pub struct Match {
     pub regex_id: usize,
     pub start: usize,
     pub end: usize,
+    /// what the match would be replaced with, set when there is a replacement
+    pub replacement: Option<Vec<u8>>,
 }
 
+/// The whole line as it is in the file, matches are byte offsets into it
+/// sorted by where they start, they can overlap when more than one
+/// pattern matched the same text.
 pub struct MatchedLine {
     pub line_num: usize,
     pub contents: Vec<u8>,
     pub matches: Vec<Match>,
 }
 
-trait SliceExt {
-    fn trim(&self) -> &Self;
+/// A span of a line where the set of patterns matching it does not change.
+pub struct Segment {
+    pub start: usize,
+    pub end: usize,
+    pub regex_ids: Vec<usize>,
 }
 
-impl SliceExt for [u8] {
-    fn trim(&self) -> &[u8] {
-        fn is_whitespace(c: &u8) -> bool {
-            *c == b'\t' || *c == b' '
+impl MatchedLine {
+    /// split the matches into segments that don't overlap, each one
+    /// holding every pattern that covers it, in order of position
+    pub fn segments(&self) -> Vec<Segment> {
+        segments(&self.matches)
+    }
+
+    /// the line with each match that has a replacement swapped for it,
+    /// the matches then cover the replaced text, a match overlapping
+    /// one before it is left as it is
+    pub fn replaced(&self) -> MatchedLine {
+        let mut contents: Vec<u8> = Vec::with_capacity(self.contents.len());
+        let mut matches: Vec<Match> = Vec::with_capacity(self.matches.len());
+        let mut last_end: usize = 0;
+        for m in self.matches.iter().filter(|m| m.replacement.is_some()) {
+            if m.start < last_end {
+                continue;
+            }
+            contents.extend_from_slice(&self.contents[last_end..m.start]);
+            let start = contents.len();
+            contents.extend_from_slice(m.replacement.as_deref().unwrap_or_default());
+            matches.push(Match {
+                regex_id: m.regex_id,
+                start,
+                end: contents.len(),
+                replacement: None,
+            });
+            last_end = m.end;
+        }
+        contents.extend_from_slice(&self.contents[last_end..]);
+        MatchedLine {
+            line_num: self.line_num,
+            contents,
+            matches,
         }
+    }
+}
+
+pub(crate) fn segments(matches: &[Match]) -> Vec<Segment> {
+    let mut bounds: Vec<usize> = matches.iter().flat_map(|m| [m.start, m.end]).collect();
+    bounds.sort_unstable();
+    bounds.dedup();
 
-        fn is_not_whitespace(c: &u8) -> bool {
-            !is_whitespace(c)
+    let mut segments: Vec<Segment> = Vec::new();
+    for pair in bounds.windows(2) {
+        let (start, end) = (pair[0], pair[1]);
+        let mut regex_ids: Vec<usize> = matches
+            .iter()
+            .filter(|m| m.start <= start && end <= m.end)
+            .map(|m| m.regex_id)
+            .collect();
+        if regex_ids.is_empty() {
+            continue;
         }
+        regex_ids.sort_unstable();
+        regex_ids.dedup();
+        match segments.last_mut() {
+            Some(last) if last.end == start && last.regex_ids == regex_ids => last.end = end,
+            _ => segments.push(Segment {
+                start,
+                end,
+                regex_ids,
+            }),
+        }
+    }
+    segments
+}
 
-        if let Some(first) = self.iter().position(is_not_whitespace) {
-            if let Some(last) = self.iter().rposition(is_not_whitespace) {
-                &self[first..last + 1]
-            } else {
-                unreachable!();
-            }
+/// What is done with files that look binary.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BinaryMode {
+    Skip,
+    /// only give the number of matches
+    Report,
+    /// search them like any other file
+    Text,
+}
+
+/// What the children and files of each directory are ordered by.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Path,
+    Modified,
+    Accessed,
+    Created,
+    Size,
+    /// matched lines, for a directory every matched line below it
+    Matches,
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    Path,
+    // entries whose time can't be read come first
+    Time(Option<SystemTime>),
+    Number(u64),
+}
+
+/// Settings used when walking and matching, built with
+/// [`SearchOptions::new`] and the chained setters.
+pub struct SearchOptions {
+    pub(crate) sources: Vec<String>,
+    pub(crate) patterns: Patterns,
+    // the patterns with `\r\n` as the line terminator, built on first use
+    pub(crate) crlf_patterns: OnceLock<Patterns>,
+    pub(crate) engine: Engine,
+    pub(crate) crlf: bool,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) hidden: bool,
+    pub(crate) max_filesize: Option<u64>,
+    pub(crate) all_match: bool,
+    pub(crate) and: bool,
+    pub(crate) path_match: bool,
+    pub(crate) sort: Option<(SortBy, bool)>,
+    pub(crate) replacement: Option<String>,
+    pub(crate) no_ignore: bool,
+    pub(crate) no_ignore_vcs: bool,
+    pub(crate) no_ignore_parent: bool,
+    pub(crate) no_ignore_global: bool,
+    pub(crate) ignore_files: Vec<PathBuf>,
+    pub(crate) all: bool,
+    pub(crate) follow: bool,
+    pub(crate) binary: BinaryMode,
+    pub(crate) encoding: Option<Encoding>,
+    pub(crate) search_zip: bool,
+    pub(crate) archives: bool,
+    pub(crate) pre: Option<PathBuf>,
+    pub(crate) pre_globs: Override,
+    pub(crate) max_count: Option<usize>,
+    pub(crate) max_total: Option<usize>,
+}
+
+impl SearchOptions {
+    pub fn new<I, S>(patterns: I) -> Result<SearchOptions, Errors>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        SearchOptions::with_engine(patterns, Engine::Regex)
+    }
+
+    /// match with PCRE2 instead of the regex crate, for look around and
+    /// back references
+    #[cfg(feature = "pcre2")]
+    pub fn new_pcre2<I, S>(patterns: I) -> Result<SearchOptions, Errors>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        SearchOptions::with_engine(patterns, Engine::Pcre2)
+    }
+
+    fn with_engine<I, S>(patterns: I, engine: Engine) -> Result<SearchOptions, Errors>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let sources: Vec<String> = patterns
+            .into_iter()
+            .map(|p| p.as_ref().to_string())
+            .collect();
+        Ok(SearchOptions {
+            patterns: Patterns::new(&sources, engine, false)?,
+            crlf_patterns: OnceLock::new(),
+            engine,
+            sources,
+            crlf: false,
+            max_depth: None,
+            hidden: false,
+            max_filesize: None,
+            all_match: false,
+            and: false,
+            path_match: false,
+            sort: None,
+            replacement: None,
+            no_ignore: false,
+            no_ignore_vcs: false,
+            no_ignore_parent: false,
+            no_ignore_global: false,
+            ignore_files: Vec::new(),
+            all: false,
+            follow: false,
+            binary: BinaryMode::Skip,
+            encoding: None,
+            search_zip: false,
+            archives: false,
+            pre: None,
+            pre_globs: Override::empty(),
+            max_count: None,
+            max_total: None,
+        })
+    }
+
+    /// treat every file as having `\r\n` line endings, otherwise it is
+    /// decided for each file by how its first line ends
+    pub fn crlf(mut self, crlf: bool) -> SearchOptions {
+        self.crlf = crlf;
+        self
+    }
+
+    fn patterns_for(&self, crlf: bool) -> &Patterns {
+        if crlf {
+            // these already compiled once without crlf so they can't fail
+            self.crlf_patterns
+                .get_or_init(|| Patterns::new(&self.sources, self.engine, true).unwrap())
         } else {
-            &[]
+            &self.patterns
+        }
+    }
+
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> SearchOptions {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// search hidden files and directories, the directories of version
+    /// control systems are still skipped
+    pub fn hidden(mut self, hidden: bool) -> SearchOptions {
+        self.hidden = hidden;
+        self
+    }
+
+    /// files larger than this many bytes are skipped while walking
+    pub fn max_filesize(mut self, max_filesize: Option<u64>) -> SearchOptions {
+        self.max_filesize = max_filesize;
+        self
+    }
+
+    /// only keep a file if every pattern matches somewhere in it
+    pub fn all_match(mut self, all_match: bool) -> SearchOptions {
+        self.all_match = all_match;
+        self
+    }
+
+    /// only keep a line if every pattern matches on it
+    pub fn and(mut self, and: bool) -> SearchOptions {
+        self.and = and;
+        self
+    }
+
+    /// match the patterns against the path of each file and directory
+    /// relative to the root instead of the contents of files
+    pub fn path_match(mut self, path_match: bool) -> SearchOptions {
+        self.path_match = path_match;
+        self
+    }
+
+    /// order the children and files of every directory, from smallest to
+    /// largest or the other way with `reverse`, ties are ordered by path
+    pub fn sort(mut self, sort: Option<SortBy>, reverse: bool) -> SearchOptions {
+        self.sort = sort.map(|by| (by, reverse));
+        self
+    }
+
+    /// expand this template for every match, with `$1`, `$name` or `${name}`
+    /// for groups and `$$` for a `$`, files are not changed
+    pub fn replace(mut self, replacement: Option<String>) -> SearchOptions {
+        self.replacement = replacement;
+        self
+    }
+
+    /// don't use any ignore files found while walking, `.gitignore`,
+    /// `.ignore`, `.gretignore` or the global ones, files given with
+    /// [`SearchOptions::ignore_files`] are still used
+    pub fn no_ignore(mut self, no_ignore: bool) -> SearchOptions {
+        self.no_ignore = no_ignore;
+        self
+    }
+
+    /// don't use `.gitignore`, `.git/info/exclude` or the global git ignore
+    pub fn no_ignore_vcs(mut self, no_ignore_vcs: bool) -> SearchOptions {
+        self.no_ignore_vcs = no_ignore_vcs;
+        self
+    }
+
+    /// don't use ignore files in the directories above the root
+    pub fn no_ignore_parent(mut self, no_ignore_parent: bool) -> SearchOptions {
+        self.no_ignore_parent = no_ignore_parent;
+        self
+    }
+
+    /// don't use the global git ignore from `core.excludesFile`
+    pub fn no_ignore_global(mut self, no_ignore_global: bool) -> SearchOptions {
+        self.no_ignore_global = no_ignore_global;
+        self
+    }
+
+    /// more files in the gitignore format, with the paths in them
+    /// relative to where gret is run
+    pub fn ignore_files(mut self, ignore_files: Vec<PathBuf>) -> SearchOptions {
+        self.ignore_files = ignore_files;
+        self
+    }
+
+    /// search everything, hidden and ignored files, the directories of
+    /// version control systems and binary files
+    pub fn all(mut self, all: bool) -> SearchOptions {
+        self.all = all;
+        self
+    }
+
+    /// walk into linked directories, a link back to a directory it is
+    /// already inside of is not followed
+    pub fn follow(mut self, follow: bool) -> SearchOptions {
+        self.follow = follow;
+        self
+    }
+
+    /// what to do with files that look binary, they are skipped by default
+    pub fn binary(mut self, binary: BinaryMode) -> SearchOptions {
+        self.binary = binary;
+        self
+    }
+
+    /// decode files without a byte order mark from this encoding, files
+    /// with one are decoded from the encoding it marks
+    pub fn encoding(mut self, encoding: Option<Encoding>) -> SearchOptions {
+        self.encoding = encoding;
+        self
+    }
+
+    /// search files ending in `.gz`, `.bz2`, `.xz`, `.zst` or `.lz4`,
+    /// gzip is decoded in-process and the others by running the program
+    /// for the format on them, files whose program can't be run are skipped
+    pub fn search_zip(mut self, search_zip: bool) -> SearchOptions {
+        self.search_zip = search_zip;
+        self
+    }
+
+    /// search `.zip`, `.jar`, `.war`, `.ear`, `.tar`, `.tar.gz` and `.tgz`
+    /// files as directories holding the files inside of them
+    pub fn archives(mut self, archives: bool) -> SearchOptions {
+        self.archives = archives;
+        self
+    }
+
+    /// search what this program writes for each file instead of the file,
+    /// it is run with the path of the file and gets the file on stdin
+    pub fn pre(mut self, pre: Option<PathBuf>) -> SearchOptions {
+        self.pre = pre;
+        self
+    }
+
+    /// only run the preprocessor on files that match one of these globs,
+    /// globs starting with `!` leave files out, with no globs it runs on all
+    pub fn pre_globs<I, S>(mut self, globs: I) -> Result<SearchOptions, Errors>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let invalid = |e: ignore::Error| Errors::InvalidGlob {
+            cause: e.to_string(),
+        };
+        let mut builder = OverrideBuilder::new(".");
+        for glob in globs {
+            builder.add(glob.as_ref()).map_err(invalid)?;
+        }
+        self.pre_globs = builder.build().map_err(invalid)?;
+        Ok(self)
+    }
+
+    /// stop reading a file after this many of its lines matched
+    pub fn max_count(mut self, max_count: Option<usize>) -> SearchOptions {
+        self.max_count = max_count;
+        self
+    }
+
+    /// stop the search after this many lines matched in all of the files,
+    /// when matching paths each matched file counts once
+    pub fn max_total(mut self, max_total: Option<usize>) -> SearchOptions {
+        self.max_total = max_total;
+        self
+    }
+
+    /// the most lines a file can give, from the limit for each file and
+    /// what is `left` of the total
+    fn line_limit(&self, left: Option<usize>) -> Option<usize> {
+        match (self.max_count, left) {
+            (Some(count), Some(left)) => Some(count.min(left)),
+            (count, left) => count.or(left),
+        }
+    }
+
+    /// searching everything reads binary files as text unless asked to report them
+    pub(crate) fn binary_mode(&self) -> BinaryMode {
+        match self.binary {
+            BinaryMode::Skip if self.all => BinaryMode::Text,
+            mode => mode,
         }
     }
 }
 
-impl File {
-    fn add_matches(&mut self, contents: Vec<u8>) {
-        // check if it is a binary file
-        if memchr(0, &contents).is_some() {
-            return;
+impl Searched {
+    /// true when nothing in the result matched
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Searched::Dir(dirs) => dirs
+                .first()
+                .is_none_or(|root| root.children.is_empty() && root.files.is_empty()),
+            Searched::File(file) => file.is_empty(),
+            Searched::Many(all) => all.iter().all(Searched::is_empty),
         }
-        // Split contents into lines
-        let lines = contents.split(|&byte| byte == b'\n');
+    }
 
-        for (line_num, line_with_whitespace) in lines.enumerate() {
-            let line = line_with_whitespace.trim();
-            let mut matches: Vec<Match> = Vec::new();
-            for (j, pattern) in CONFIG.patterns.iter().enumerate() {
-                let mut it = pattern.find_iter(&line).peekable();
-                if it.peek().is_none() {
-                    continue;
+    /// keep only the files `f` returns true for, directories left
+    /// without files below them are taken out of the tree
+    pub(crate) fn retain_files(&mut self, f: &mut impl FnMut(&mut File) -> bool) {
+        match self {
+            Searched::Dir(dirs) => {
+                // a directory is always found before the ones in it, so
+                // going backwards each child is decided before its parent
+                let mut keep: Vec<bool> = vec![false; dirs.len()];
+                for id in (0..dirs.len()).rev() {
+                    dirs[id].files.retain_mut(&mut *f);
+                    keep[id] =
+                        !dirs[id].files.is_empty() || dirs[id].children.iter().any(|&c| keep[c]);
                 }
-                for m in it {
-                    matches.push(Match {
-                        regex_id: j,
-                        start: m.start(),
-                        end: m.end(),
-                    });
+                for dir in dirs.iter_mut() {
+                    dir.children.retain(|&c| keep[c]);
+                }
+            }
+            Searched::File(file) => {
+                if !f(file) {
+                    *self = Searched::Many(Vec::new());
+                }
+            }
+            Searched::Many(all) => {
+                for s in all {
+                    s.retain_files(f);
+                }
+            }
+        }
+    }
+}
+
+impl File {
+    fn new(path: PathBuf) -> Result<File, Errors> {
+        Ok(File {
+            lines: Vec::new(),
+            name: path_name(&path)?,
+            name_matches: Vec::new(),
+            linked: linked_path(&path),
+            path,
+            binary: None,
+            binary_matches: None,
+            encoding: None,
+            bom: false,
+            compressed: false,
+            preprocessed: false,
+            archived: None,
+            read_stamp: None,
+        })
+    }
+
+    /// true when neither the contents nor the name matched
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty() && self.name_matches.is_empty() && self.binary_matches.is_none()
+    }
+
+    /// search the contents, keeping at most `limit` lines
+    fn add_matches(
+        &mut self,
+        reader: impl Read,
+        options: &SearchOptions,
+        limit: Option<usize>,
+    ) -> io::Result<()> {
+        let mut buffer = LineBuffer::new(reader);
+        // lines in the blocks before the current one
+        let mut lines_before: usize = 0;
+        let mut crlf: Option<bool> = None;
+        while let Some(block) = buffer.next_block()? {
+            // only the start of the file decides if it is binary
+            if self.binary.is_none() {
+                let binary = is_binary(&block[..block.len().min(BINARY_PREFIX_LEN)]);
+                self.binary = Some(binary);
+                if binary && options.binary_mode() == BinaryMode::Skip {
+                    return Ok(());
                 }
             }
-            if matches.len() > 0 {
-                // parse through and set the overlapping to have no issues
-                matches.sort_by_key(|m| m.end);
+            let crlf = *crlf.get_or_insert_with(|| options.crlf || ends_with_crlf(block));
+            let patterns = options.patterns_for(crlf);
+            // skip the per line work if no pattern matches anywhere in the block
+            let active: Vec<usize> = patterns.matching(block)?;
+            // with `and` a line can't match if a pattern is missing from its block
+            let skip = active.is_empty() || (options.and && active.len() < options.sources.len());
+            if !skip {
+                self.search_block(block, lines_before, &active, patterns, crlf, options)?;
+            }
+            if self.is_full(options, limit) {
+                break;
+            }
+            lines_before += memchr_iter(b'\n', block).count();
+        }
+        if options.all_match && !self.matches_all(options.sources.len()) {
+            self.lines.clear();
+        }
+        if let Some(max) = limit {
+            self.lines.truncate(max);
+        }
+        if self.binary == Some(true) && options.binary_mode() == BinaryMode::Report {
+            let count: usize = self.lines.iter().map(|line| line.matches.len()).sum();
+            self.lines.clear();
+            self.binary_matches = (count > 0).then_some(count);
+        }
+        Ok(())
+    }
 
-                let mut m_id = 1;
-                while m_id < matches.len() {
-                    // if this one starts before the previous ended
-                    if matches[m_id].start < matches[m_id - 1].end {
-                        // Overlap found
-                        matches[m_id].start = matches[m_id - 1].end;
-                    }
-                    m_id += 1;
+    /// whether `limit` lines were found, with `all_match` the file is read
+    /// on until every pattern is seen
+    fn is_full(&self, options: &SearchOptions, limit: Option<usize>) -> bool {
+        limit.is_some_and(|max| self.lines.len() >= max)
+            && (!options.all_match || self.matches_all(options.sources.len()))
+    }
+
+    /// whether each of the `count` patterns matched on one of the lines
+    fn matches_all(&self, count: usize) -> bool {
+        let mut seen: Vec<bool> = vec![false; count];
+        for m in self.lines.iter().flat_map(|line| &line.matches) {
+            seen[m.regex_id] = true;
+        }
+        seen.into_iter().all(|s| s)
+    }
+
+    /// find each line holding a match for one of the `active` patterns,
+    /// the earliest hit gives the next line to check and the search
+    /// continues from the start of the line after it
+    fn search_block(
+        &mut self,
+        block: &[u8],
+        lines_before: usize,
+        active: &[usize],
+        patterns: &Patterns,
+        crlf: bool,
+        options: &SearchOptions,
+    ) -> io::Result<()> {
+        if patterns.line_by_line(active) {
+            return self.search_lines(block, lines_before, active, patterns, crlf, options);
+        }
+        // the next hit of each active pattern, found again once it is behind `pos`
+        let mut next_hits: Vec<Option<usize>> = active
+            .iter()
+            .map(|&id| Ok(patterns.matchers[id].find_at(block, 0)?.map(|m| m.0)))
+            .collect::<io::Result<Vec<Option<usize>>>>()?;
+        let mut pos: usize = 0;
+        let mut line_num: usize = lines_before;
+        let mut counted_to: usize = 0;
+        while pos < block.len() {
+            for (hit, &id) in next_hits.iter_mut().zip(active) {
+                if hit.is_some_and(|start| start < pos) {
+                    *hit = patterns.matchers[id].find_at(block, pos)?.map(|m| m.0);
                 }
-                self.lines.push(MatchedLine {
-                    contents: line.to_vec(),
-                    matches,
-                    line_num: line_num + 1,
+            }
+            let start = match next_hits.iter().flatten().min() {
+                Some(&start) => start,
+                None => break,
+            };
+            let line_start = memrchr(b'\n', &block[pos..start]).map_or(pos, |i| pos + i + 1);
+            let line_end = memchr(b'\n', &block[start..]).map_or(block.len(), |i| start + i);
+            line_num += memchr_iter(b'\n', &block[counted_to..line_start]).count();
+            counted_to = line_start;
+            let mut line = &block[line_start..line_end];
+            if crlf {
+                // the `\r` is last so offsets from the start of the line don't change
+                line = line.strip_suffix(b"\r").unwrap_or(line);
+            }
+            self.match_line(line, line_num + 1, active, patterns, options)?;
+            pos = line_end + 1;
+        }
+        Ok(())
+    }
+
+    /// run the `active` patterns on every line of the block on its own, for
+    /// patterns whose hits in the whole block don't give the lines to check
+    fn search_lines(
+        &mut self,
+        block: &[u8],
+        lines_before: usize,
+        active: &[usize],
+        patterns: &Patterns,
+        crlf: bool,
+        options: &SearchOptions,
+    ) -> io::Result<()> {
+        let lines = block
+            .strip_suffix(b"\n")
+            .unwrap_or(block)
+            .split(|&b| b == b'\n');
+        for (i, mut line) in lines.enumerate() {
+            if crlf {
+                line = line.strip_suffix(b"\r").unwrap_or(line);
+            }
+            self.match_line(line, lines_before + i + 1, active, patterns, options)?;
+        }
+        Ok(())
+    }
+
+    fn match_line(
+        &mut self,
+        line: &[u8],
+        line_num: usize,
+        active: &[usize],
+        patterns: &Patterns,
+        options: &SearchOptions,
+    ) -> io::Result<()> {
+        let mut matches: Vec<Match> = Vec::new();
+        for &j in active {
+            let found = patterns.matchers[j].find_iter(line)?;
+            if options.and && found.is_empty() {
+                return Ok(());
+            }
+            for (start, end) in found {
+                let replacement = match &options.replacement {
+                    Some(template) => {
+                        let mut dst: Vec<u8> = Vec::new();
+                        patterns.matchers[j].expand_at(line, start, template, &mut dst)?;
+                        Some(dst)
+                    }
+                    None => None,
+                };
+                matches.push(Match {
+                    regex_id: j,
+                    start,
+                    end,
+                    replacement,
                 });
             }
         }
+        if !matches.is_empty() {
+            matches.sort_by_key(|m| (m.start, m.end));
+            self.lines.push(MatchedLine {
+                contents: line.to_vec(),
+                matches,
+                line_num,
+            });
+        }
+        Ok(())
     }
 }
 
@@ -120,6 +743,8 @@ impl Directory {
         Directory {
             files: Vec::new(),
             children: Vec::new(),
+            name_matches: Vec::new(),
+            linked: linked_path(Path::new(&path)),
             to_add: true,
             name,
             path,
@@ -127,101 +752,615 @@ impl Directory {
     }
 }
 
-pub fn search_dir(root_path: PathBuf) -> Result<Vec<Directory>, Errors> {
-    let walker = WalkBuilder::new(&root_path)
-        .hidden(!CONFIG.search_hidden)
-        .max_depth(CONFIG.max_depth)
-        .build();
+/// Search each root, directories are walked and files are searched directly.
+/// A single root gives back its own result, more than one gives [`Searched::Many`].
+pub fn search<P: AsRef<Path>>(roots: &[P], options: &SearchOptions) -> Result<Searched, Errors> {
+    search_with_skipped(roots, options).map(|(searched, _)| searched)
+}
+
+/// [`search`], also giving back the entries that were left out while
+/// searching, like binary files, files that couldn't be read and links
+/// that loop. Entries that the ignore rules leave out are given by
+/// [`crate::ignored`].
+pub fn search_with_skipped<P: AsRef<Path>>(
+    roots: &[P],
+    options: &SearchOptions,
+) -> Result<(Searched, Vec<Skipped>), Errors> {
+    let mut all: Vec<Searched> = Vec::with_capacity(roots.len());
+    let mut progress = Progress {
+        left: options.max_total,
+        skipped: Vec::new(),
+    };
+    for root in roots {
+        if progress.left == Some(0) {
+            break;
+        }
+        // without a trailing `/` or `.` the walker's paths start with the
+        // root as it is kept, like `sub` for `sub/./`
+        let path: PathBuf = root.as_ref().components().collect();
+        if !path.exists() {
+            return Err(Errors::PathNotFound { cause: path });
+        }
+        if path.is_dir() {
+            all.push(Searched::Dir(search_dir(path, options, &mut progress)?));
+        } else if let Some(kind) = options.archives.then(|| archive_kind(&path)).flatten() {
+            let mut directories: Vec<Directory> = Vec::new();
+            let mut path_to_index: HashMap<OsString, usize> = HashMap::new();
+            add_dir(&mut directories, &mut path_to_index, &path, &path, options)?;
+            let read: bool = search_archive(
+                &mut directories,
+                &mut path_to_index,
+                &path,
+                &path,
+                kind,
+                options,
+                &mut progress,
+            )?;
+            if !read {
+                return Err(failed_to_read(&path, &mut progress));
+            }
+            all.push(Searched::Dir(directories));
+        } else if options.path_match {
+            let parent: PathBuf = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            let file = name_file(path, &parent, options)?;
+            take(&mut progress.left, &file);
+            all.push(Searched::File(file));
+        } else {
+            let limit = options.line_limit(progress.left);
+            let file = match search_file(path.clone(), options, limit, &mut progress.skipped)? {
+                Some(file) => file,
+                None => return Err(failed_to_read(&path, &mut progress)),
+            };
+            take(&mut progress.left, &file);
+            all.push(Searched::File(file));
+        }
+    }
+    let searched = if all.len() == 1 {
+        all.pop().unwrap()
+    } else {
+        Searched::Many(all)
+    };
+    Ok((searched, progress.skipped))
+}
+
+/// What carries on from one entry of a search to the next.
+struct Progress {
+    /// how many more lines can be found
+    left: Option<usize>,
+    skipped: Vec<Skipped>,
+}
+
+/// take what a matched file found from what is `left` of the total
+fn take(left: &mut Option<usize>, file: &File) {
+    if let Some(left) = left {
+        if !file.is_empty() {
+            *left = left.saturating_sub(file.lines.len().max(1));
+        }
+    }
+}
+
+impl Progress {
+    fn skip(&mut self, path: PathBuf, reason: SkipReason) {
+        self.skipped.push(Skipped { path, reason });
+    }
+}
+
+/// the error for a target that couldn't be read, with the reason it was skipped
+fn failed_to_read(path: &Path, progress: &mut Progress) -> Errors {
+    let reason: String = match progress.skipped.pop() {
+        Some(skip) if skip.path == path => format!(", {}", skip.reason),
+        _ => String::new(),
+    };
+    Errors::IOError {
+        cause: format!("failed to read `{}`{}", path.display(), reason),
+    }
+}
+
+/// the walker for the search of `root`, the filter for version control
+/// directories is left to the caller since a walker has only one filter
+pub(crate) fn walk_builder(root: &Path, options: &SearchOptions) -> Result<WalkBuilder, Errors> {
+    let no_ignore: bool = options.no_ignore || options.all;
+    let vcs: bool = !no_ignore && !options.no_ignore_vcs;
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(!options.hidden && !options.all)
+        .max_depth(options.max_depth)
+        .follow_links(options.follow)
+        .ignore(!no_ignore)
+        .git_ignore(vcs)
+        .git_exclude(vcs)
+        .git_global(vcs && !options.no_ignore_global)
+        .parents(!no_ignore && !options.no_ignore_parent);
+    if !no_ignore {
+        builder.add_custom_ignore_filename(IGNORE_FILE_NAME);
+    }
+    for path in options.ignore_files.iter() {
+        // the error already names the path
+        if let Some(e) = builder.add_ignore(path) {
+            return Err(Errors::IOError {
+                cause: format!("failed to read ignore file {}", e),
+            });
+        }
+    }
+    Ok(builder)
+}
+
+fn search_dir(
+    root_path: PathBuf,
+    options: &SearchOptions,
+    progress: &mut Progress,
+) -> Result<Vec<Directory>, Errors> {
+    let mut builder = walk_builder(&root_path, options)?;
+    if !options.all {
+        builder.filter_entry(|entry| !is_vcs_dir(entry));
+    }
+    let walker = builder.build();
 
     let mut path_to_index: HashMap<OsString, usize> = HashMap::new();
     let mut directories: Vec<Directory> = Vec::new();
-    for res in walker {
-        match res {
-            Ok(entry) => {
-                let path = entry.into_path();
-                if path.is_dir() {
-                    let name: String = path_name(&path)?;
-                    if path_to_index.get(path.as_os_str()).is_none() {
-                        path_to_index.insert(path.clone().into_os_string(), directories.len());
-                        let dir = Directory::new(name, path.into_os_string());
+    for result in walker {
+        // the rest of the walk is left out once the total is found
+        if progress.left == Some(0) {
+            break;
+        }
+        let entry = match result {
+            Ok(entry) => entry,
+            Err(e) => {
+                progress.skipped.push(walk_error(&e, &root_path));
+                continue;
+            }
+        };
+        let path = entry.into_path();
+        if path.is_dir() {
+            let name: String = path_name(&path)?;
+            if !path_to_index.contains_key(path.as_os_str()) {
+                let id = directories.len();
+                path_to_index.insert(path.clone().into_os_string(), id);
+                let mut dir = Directory::new(name, path.clone().into_os_string());
+                if options.path_match && path != root_path {
+                    dir.name_matches = match_name(&path, &root_path, &dir.name, options)?;
+                    if !dir.name_matches.is_empty() {
                         directories.push(dir);
+                        add_to_tree(&mut directories, &path_to_index, &root_path, path);
+                        continue;
                     }
-                } else if path.is_file() {
-                    let m_file = search_file(path)?;
-                    if let Some(file) = m_file.and_then(|file| {
-                        if file.lines.len() > 0 {
-                            Some(file)
-                        } else {
-                            None
-                        }
-                    }) {
-                        if let Some(mut dir_path) = file.path.parent().map(|v| v.to_path_buf()) {
-                            let mut prev_id: usize =
-                                *path_to_index.get(dir_path.as_os_str()).unwrap();
-                            let mut dir: &mut Directory = directories.get_mut(prev_id).unwrap();
-                            dir.files.push(file);
-                            let mut to_add = dir.to_add;
-                            while let Some(par_dir_path) = dir_path.parent() {
-                                if !to_add || dir_path == root_path {
-                                    break;
-                                }
-                                dir.to_add = false;
-                                let t = *path_to_index.get(par_dir_path.as_os_str()).unwrap();
-                                dir = directories.get_mut(t).unwrap();
-                                dir.children.push(prev_id);
-                                prev_id = t;
-                                to_add = dir.to_add;
-                                dir_path = par_dir_path.to_path_buf();
-                            }
-                        }
-                    }
+                }
+                directories.push(dir);
+            }
+        } else if path.is_file() {
+            if let Some(kind) = options.archives.then(|| archive_kind(&path)).flatten() {
+                // an archive that can't be read is skipped like a file that can't be
+                search_archive(
+                    &mut directories,
+                    &mut path_to_index,
+                    &root_path,
+                    &path,
+                    kind,
+                    options,
+                    progress,
+                )?;
+                continue;
+            }
+            if let Some(max) = options.max_filesize {
+                if entry_len(&path) > max {
+                    progress.skip(path, SkipReason::TooLarge);
+                    continue;
+                }
+            }
+            let m_file = if options.path_match {
+                Some(name_file(path, &root_path, options)?)
+            } else {
+                let limit = options.line_limit(progress.left);
+                search_file(path, options, limit, &mut progress.skipped)?
+            };
+            if let Some(file) = m_file.filter(|file| !file.is_empty()) {
+                take(&mut progress.left, &file);
+                if let Some(dir_path) = file.path.parent().map(|v| v.to_path_buf()) {
+                    let id: usize = *path_to_index.get(dir_path.as_os_str()).unwrap();
+                    directories[id].files.push(file);
+                    add_to_tree(&mut directories, &path_to_index, &root_path, dir_path);
                 }
             }
-            _ => {}
         }
     }
+    if let Some((by, reverse)) = options.sort {
+        sort_tree(&mut directories, by, reverse);
+    }
     Ok(directories)
 }
 
-pub fn search_file(pb: PathBuf) -> Result<Option<File>, Errors> {
-    let m_content_bytes: Option<Vec<u8>> = fs::read(&pb).ok();
+/// add the files of the archive at `path` that match under a directory
+/// for it and directories for the paths inside of it, made like the
+/// directories that are walked
+fn search_archive(
+    directories: &mut Vec<Directory>,
+    path_to_index: &mut HashMap<OsString, usize>,
+    root_path: &Path,
+    path: &Path,
+    kind: ArchiveKind,
+    options: &SearchOptions,
+    progress: &mut Progress,
+) -> Result<bool, Errors> {
+    // shared by `read` and `found`
+    let entries_left: Cell<Option<usize>> = Cell::new(progress.left);
+    // only the names are needed when matching paths
+    let read = |_: &str, size: u64| {
+        !options.path_match
+            && options.max_filesize.is_none_or(|max| size <= max)
+            && entries_left.get() != Some(0)
+    };
+    let mut files: Vec<File> = Vec::new();
+    let mut skipped: Vec<Skipped> = Vec::new();
+    let mut error: Option<Errors> = None;
+    let mut found = |name: String, contents: Contents| {
+        if entries_left.get() == Some(0) {
+            return;
+        }
+        let entry_path: PathBuf = path.join(&name);
+        let file = match contents {
+            _ if options.path_match => name_file(entry_path, root_path, options).map(Some),
+            Contents::Reader(reader) => File::new(entry_path).map(|mut file| {
+                let limit = options.line_limit(entries_left.get());
+                read_matches(&mut file, reader, options, limit, &mut skipped).then_some(file)
+            }),
+            Contents::Unreadable(e) => {
+                skipped.push(Skipped {
+                    path: entry_path,
+                    reason: SkipReason::ReadError(e.to_string()),
+                });
+                Ok(None)
+            }
+            Contents::NotRead => {
+                skipped.push(Skipped {
+                    path: entry_path,
+                    reason: SkipReason::TooLarge,
+                });
+                Ok(None)
+            }
+        };
+        match file {
+            Ok(Some(mut file)) if !file.is_empty() => {
+                let mut taken = entries_left.get();
+                take(&mut taken, &file);
+                entries_left.set(taken);
+                file.archived = Some(ArchiveEntry {
+                    archive: path.to_path_buf(),
+                    name,
+                });
+                files.push(file);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    };
+    let read = read_archive(path, kind, &read, &mut found);
+    progress.left = entries_left.get();
+    progress.skipped.append(&mut skipped);
+    if let Err(e) = read {
+        progress.skip(path.to_path_buf(), SkipReason::ReadError(e.to_string()));
+        return Ok(false);
+    }
+    if let Some(e) = error {
+        return Err(e);
+    }
 
-    let content_bytes: Vec<u8>;
-    match m_content_bytes {
-        None => return Ok(None),
-        Some(b) => content_bytes = b,
+    add_dir(directories, path_to_index, root_path, path, options)?;
+    for file in files {
+        let dir_path: PathBuf = file.path.parent().unwrap_or(path).to_path_buf();
+        // parents are added before their children
+        for dir in dir_path.ancestors().collect::<Vec<_>>().into_iter().rev() {
+            if dir.starts_with(path) {
+                add_dir(directories, path_to_index, root_path, dir, options)?;
+            }
+        }
+        let id: usize = *path_to_index.get(dir_path.as_os_str()).unwrap();
+        directories[id].files.push(file);
+        add_to_tree(directories, path_to_index, root_path, dir_path);
     }
+    Ok(true)
+}
 
-    let linked: Option<PathBuf> =
-        fs::read_link(&pb)
-            .ok()
-            .and_then(|target_path| match std::env::var("HOME").ok() {
-                Some(home) => {
-                    if target_path.starts_with(&home) {
-                        target_path
-                            .strip_prefix(&home)
-                            .ok()
-                            .map(|clean_path| PathBuf::from("~").join(clean_path))
-                    } else {
-                        Some(target_path)
-                    }
+/// add a directory that isn't walked, like one inside of an archive, if it
+/// isn't there yet, it is linked to its parent when its name matches
+fn add_dir(
+    directories: &mut Vec<Directory>,
+    path_to_index: &mut HashMap<OsString, usize>,
+    root_path: &Path,
+    path: &Path,
+    options: &SearchOptions,
+) -> Result<(), Errors> {
+    if path_to_index.contains_key(path.as_os_str()) {
+        return Ok(());
+    }
+    path_to_index.insert(path.as_os_str().to_os_string(), directories.len());
+    let mut dir = Directory::new(path_name(path)?, path.as_os_str().to_os_string());
+    let matched = options.path_match && path != root_path && {
+        dir.name_matches = match_name(path, root_path, &dir.name, options)?;
+        !dir.name_matches.is_empty()
+    };
+    directories.push(dir);
+    if matched {
+        add_to_tree(directories, path_to_index, root_path, path.to_path_buf());
+    }
+    Ok(())
+}
+
+fn sort_tree(directories: &mut [Directory], by: SortBy, reverse: bool) {
+    // a directory is always found before the ones in it, so going
+    // backwards each child's total is known before its parent's
+    let mut totals: Vec<usize> = vec![0; directories.len()];
+    for id in (0..directories.len()).rev() {
+        let dir = &directories[id];
+        totals[id] = dir.files.iter().map(|f| f.lines.len()).sum::<usize>()
+            + dir.children.iter().map(|&c| totals[c]).sum::<usize>();
+    }
+    for id in 0..directories.len() {
+        let mut children: Vec<usize> = std::mem::take(&mut directories[id].children);
+        children.sort_by_cached_key(|&c| {
+            let path = Path::new(&directories[c].path);
+            (sort_key(path, by, totals[c]), path.to_path_buf())
+        });
+        let dir = &mut directories[id];
+        dir.children = children;
+        dir.files
+            .sort_by_cached_key(|f| (sort_key(&f.path, by, f.lines.len()), f.path.clone()));
+        if reverse {
+            dir.children.reverse();
+            dir.files.reverse();
+        }
+    }
+}
+
+fn sort_key(path: &Path, by: SortBy, matches: usize) -> SortKey {
+    let metadata = || fs::metadata(path).ok();
+    match by {
+        SortBy::Path => SortKey::Path,
+        SortBy::Modified => SortKey::Time(metadata().and_then(|m| m.modified().ok())),
+        SortBy::Accessed => SortKey::Time(metadata().and_then(|m| m.accessed().ok())),
+        SortBy::Created => SortKey::Time(metadata().and_then(|m| m.created().ok())),
+        SortBy::Size => SortKey::Number(metadata().map_or(0, |m| m.len())),
+        SortBy::Matches => SortKey::Number(matches as u64),
+    }
+}
+
+/// link the directory at `dir_path` and the ones above it to their parents
+/// until reaching the root or a directory that is already linked
+fn add_to_tree(
+    directories: &mut [Directory],
+    path_to_index: &HashMap<OsString, usize>,
+    root_path: &Path,
+    mut dir_path: PathBuf,
+) {
+    let mut prev_id: usize = *path_to_index.get(dir_path.as_os_str()).unwrap();
+    let mut to_add = directories[prev_id].to_add;
+    while let Some(par_dir_path) = dir_path.parent() {
+        if !to_add || dir_path == root_path {
+            break;
+        }
+        directories[prev_id].to_add = false;
+        let t = *path_to_index.get(par_dir_path.as_os_str()).unwrap();
+        directories[t].children.push(prev_id);
+        prev_id = t;
+        to_add = directories[t].to_add;
+        dir_path = par_dir_path.to_path_buf();
+    }
+}
+
+/// a file with only the matches in its name, for searching paths
+fn name_file(pb: PathBuf, root_path: &Path, options: &SearchOptions) -> Result<File, Errors> {
+    let mut file = File::new(pb)?;
+    file.name_matches = match_name(&file.path, root_path, &file.name, options)?;
+    Ok(file)
+}
+
+/// run the patterns on the path relative to `root_path`, a match counts
+/// when it ends inside of `name` so entries below a matched directory
+/// aren't all matched too, the kept matches are cut to `name`
+fn match_name(
+    path: &Path,
+    root_path: &Path,
+    name: &str,
+    options: &SearchOptions,
+) -> Result<Vec<Match>, Errors> {
+    let relative: String = path
+        .strip_prefix(root_path)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .into_owned();
+    let name_start: usize = match relative.strip_suffix(name) {
+        Some(parents) => parents.len(),
+        None => return Ok(Vec::new()),
+    };
+    let mut matches: Vec<Match> = Vec::new();
+    for (regex_id, matcher) in options.patterns.matchers.iter().enumerate() {
+        let found = matcher
+            .find_iter(relative.as_bytes())
+            .map_err(|e| Errors::IOError {
+                cause: format!("failed to match `{}`: {}", relative, e),
+            })?;
+        for (start, end) in found {
+            if end > name_start {
+                matches.push(Match {
+                    regex_id,
+                    start: start.max(name_start) - name_start,
+                    end: end - name_start,
+                    replacement: None,
+                });
+            }
+        }
+    }
+    matches.sort_by_key(|m| (m.start, m.end));
+    Ok(matches)
+}
+
+/// the file with its matches, `None` if it couldn't be read in which
+/// case why is added to `skipped`
+fn search_file(
+    pb: PathBuf,
+    options: &SearchOptions,
+    limit: Option<usize>,
+    skipped: &mut Vec<Skipped>,
+) -> Result<Option<File>, Errors> {
+    let reader: fs::File = match fs::File::open(&pb) {
+        Ok(r) => r,
+        Err(e) => {
+            skipped.push(Skipped {
+                path: pb,
+                reason: SkipReason::ReadError(e.to_string()),
+            });
+            return Ok(None);
+        }
+    };
+
+    let read_stamp = reader
+        .metadata()
+        .ok()
+        .and_then(|m| Some((m.modified().ok()?, m.len())));
+    let mut file = File::new(pb)?;
+    file.read_stamp = read_stamp;
+
+    let reader: Box<dyn Read> = match open_contents(&file.path, reader, options) {
+        Ok((reader, through)) => {
+            file.compressed = through == Through::Decompressor;
+            file.preprocessed = through == Through::Preprocessor;
+            reader
+        }
+        Err(e) => {
+            // a preprocessor that isn't there would fail on every file
+            if e.kind() == io::ErrorKind::NotFound {
+                if let Some(pre) = preprocessor(&file.path, options) {
+                    return Err(Errors::PreprocessorNotFound {
+                        cause: pre.to_path_buf(),
+                    });
                 }
-                None => Some(target_path),
+            }
+            skipped.push(Skipped {
+                path: file.path,
+                reason: SkipReason::ReadError(e.to_string()),
             });
+            return Ok(None);
+        }
+    };
+    Ok(read_matches(&mut file, reader, options, limit, skipped).then_some(file))
+}
+
+/// the program the contents of a file were read through
+#[derive(PartialEq, Eq)]
+pub(crate) enum Through {
+    Nothing,
+    Decompressor,
+    Preprocessor,
+}
+
+/// what to search for the file at `path`, the output of the preprocessor
+/// when it runs on the file, else the output of its decompressor when
+/// searching compressed files, else the file
+pub(crate) fn open_contents(
+    path: &Path,
+    file: fs::File,
+    options: &SearchOptions,
+) -> io::Result<(Box<dyn Read>, Through)> {
+    if let Some(pre) = preprocessor(path, options) {
+        let mut command = Command::new(pre);
+        command.arg(path);
+        let output = CommandOutput::spawn(command, file, pre.display().to_string())?;
+        return Ok((Box::new(output), Through::Preprocessor));
+    }
+    if let Some(decompressor) = options.search_zip.then(|| decompressor(path)).flatten() {
+        return Ok((decompressor.open(file)?, Through::Decompressor));
+    }
+    Ok((Box::new(file), Through::Nothing))
+}
+
+/// the preprocessor that `path` is read through, if its globs take it
+fn preprocessor<'a>(path: &Path, options: &'a SearchOptions) -> Option<&'a Path> {
+    let pre: &Path = options.pre.as_deref()?;
+    (!options.pre_globs.matched(path, false).is_ignore()).then_some(pre)
+}
 
-    let mut file = File {
-        lines: Vec::new(),
-        name: path_name(&pb)?,
-        path: pb,
-        linked,
+/// decode and search what `reader` gives, false if it couldn't be read,
+/// why the file was left out is added to `skipped`, a binary file is
+/// left out but still read
+fn read_matches(
+    file: &mut File,
+    reader: impl Read,
+    options: &SearchOptions,
+    limit: Option<usize>,
+    skipped: &mut Vec<Skipped>,
+) -> bool {
+    let read = DecodeReader::new(reader, options.encoding).and_then(|mut reader| {
+        file.add_matches(&mut reader, options, limit)?;
+        Ok(reader)
+    });
+    let reader = match read {
+        Ok(reader) => reader,
+        Err(e) => {
+            skipped.push(Skipped {
+                path: file.path.clone(),
+                reason: SkipReason::ReadError(e.to_string()),
+            });
+            return false;
+        }
     };
+    if file.binary == Some(true) && options.binary_mode() == BinaryMode::Skip {
+        skipped.push(Skipped {
+            path: file.path.clone(),
+            reason: SkipReason::Binary,
+        });
+    }
+    file.encoding = reader.encoding();
+    file.bom = reader.bom();
+    true
+}
+
+pub(crate) fn is_vcs_dir(entry: &DirEntry) -> bool {
+    entry.file_type().is_some_and(|t| t.is_dir())
+        && VCS_DIRS.iter().any(|name| entry.file_name() == *name)
+}
+
+/// where a link points, with `~` in place of the home directory
+fn linked_path(path: &Path) -> Option<PathBuf> {
+    fs::read_link(path)
+        .ok()
+        .and_then(|target_path| match std::env::var("HOME").ok() {
+            Some(home) => {
+                if target_path.starts_with(&home) {
+                    target_path
+                        .strip_prefix(&home)
+                        .ok()
+                        .map(|clean_path| PathBuf::from("~").join(clean_path))
+                } else {
+                    Some(target_path)
+                }
+            }
+            None => Some(target_path),
+        })
+}
+
+/// a NUL byte means binary, otherwise too many control bytes do, which
+/// catches binary data without NULs, bytes that aren't UTF-8 don't count
+/// since text in legacy encodings like cp1251 or Shift_JIS is full of them
+pub(crate) fn is_binary(prefix: &[u8]) -> bool {
+    if memchr(0, prefix).is_some() {
+        return true;
+    }
+    let is_control = |b: &u8| b.is_ascii_control() && !b"\t\n\r\x0c\x1b\x08".contains(b);
+    let odd: usize = prefix.iter().filter(|b| is_control(b)).count();
+    odd * 100 > prefix.len() * BINARY_PERCENT
+}
 
-    file.add_matches(content_bytes);
+/// whether the first line of the block ends with `\r\n`
+fn ends_with_crlf(block: &[u8]) -> bool {
+    memchr(b'\n', block).is_some_and(|i| i > 0 && block[i - 1] == b'\r')
+}
 
-    return Ok(Some(file));
+fn entry_len(path: &Path) -> u64 {
+    fs::metadata(path).map_or(0, |m| m.len())
 }
 
-fn path_name(path: &PathBuf) -> Result<String, Errors> {
+fn path_name(path: &Path) -> Result<String, Errors> {
     let name = path.file_name().ok_or(Errors::CantGetName {
         cause: path.to_path_buf(),
     })?;
@@ -232,3 +1371,405 @@ fn path_name(path: &PathBuf) -> Result<String, Errors> {
             cause: path.to_path_buf(),
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn m(regex_id: usize, start: usize, end: usize, replacement: Option<&str>) -> Match {
+        Match {
+            regex_id,
+            start,
+            end,
+            replacement: replacement.map(|r| r.as_bytes().to_vec()),
+        }
+    }
+
+    fn spans(segments: &[Segment]) -> Vec<(usize, usize, Vec<usize>)> {
+        segments
+            .iter()
+            .map(|s| (s.start, s.end, s.regex_ids.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn segments_of_nested_and_identical_matches() {
+        assert_eq!(
+            spans(&segments(&[m(0, 0, 10, None), m(1, 2, 4, None)])),
+            vec![(0, 2, vec![0]), (2, 4, vec![0, 1]), (4, 10, vec![0])]
+        );
+        assert_eq!(
+            spans(&segments(&[
+                m(1, 2, 5, None),
+                m(0, 2, 5, None),
+                m(1, 2, 5, None)
+            ])),
+            vec![(2, 5, vec![0, 1])]
+        );
+        assert_eq!(
+            spans(&segments(&[m(0, 0, 4, None), m(1, 2, 6, None)])),
+            vec![(0, 2, vec![0]), (2, 4, vec![0, 1]), (4, 6, vec![1])]
+        );
+    }
+
+    #[test]
+    fn segments_of_adjacent_and_empty_matches() {
+        assert_eq!(
+            spans(&segments(&[m(0, 0, 3, None), m(1, 3, 6, None)])),
+            vec![(0, 3, vec![0]), (3, 6, vec![1])]
+        );
+        // the same patterns next to each other show as one
+        assert_eq!(
+            spans(&segments(&[m(0, 0, 3, None), m(0, 3, 6, None)])),
+            vec![(0, 6, vec![0])]
+        );
+        assert_eq!(spans(&segments(&[m(0, 3, 3, None)])), vec![]);
+        assert_eq!(
+            spans(&segments(&[m(1, 0, 5, None), m(0, 3, 3, None)])),
+            vec![(0, 5, vec![1])]
+        );
+        assert_eq!(spans(&segments(&[])), vec![]);
+    }
+
+    fn replace(contents: &str, matches: Vec<Match>) -> (String, Vec<(usize, usize)>) {
+        let line = MatchedLine {
+            line_num: 1,
+            contents: contents.as_bytes().to_vec(),
+            matches,
+        };
+        let replaced = line.replaced();
+        let spans = replaced.matches.iter().map(|m| (m.start, m.end)).collect();
+        (String::from_utf8(replaced.contents).unwrap(), spans)
+    }
+
+    #[test]
+    fn replaced_keeps_every_other_byte() {
+        let text = "abcdefgh";
+        // each match replaced with its own text gives the line back
+        let cases: Vec<Vec<(usize, usize)>> = vec![
+            vec![(0, 8), (2, 4)],
+            vec![(2, 5), (2, 5)],
+            vec![(0, 3), (3, 6)],
+            vec![(3, 3), (3, 3), (3, 6)],
+            vec![(0, 0), (8, 8)],
+        ];
+        for case in cases {
+            let matches: Vec<Match> = case
+                .iter()
+                .map(|&(start, end)| m(0, start, end, Some(&text[start..end])))
+                .collect();
+            assert_eq!(replace(text, matches).0, text, "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn replaced_spans() {
+        // a nested match is left as it is
+        assert_eq!(
+            replace(
+                "abcdefgh",
+                vec![m(0, 1, 7, Some("X")), m(1, 2, 4, Some("Y"))]
+            ),
+            ("aXh".to_string(), vec![(1, 2)])
+        );
+        // of identical matches only the first is used
+        assert_eq!(
+            replace(
+                "abcdefgh",
+                vec![m(0, 2, 5, Some("XY")), m(1, 2, 5, Some("Z"))]
+            ),
+            ("abXYfgh".to_string(), vec![(2, 4)])
+        );
+        assert_eq!(
+            replace(
+                "abcdef",
+                vec![m(0, 0, 3, Some("1")), m(0, 3, 6, Some("22"))]
+            ),
+            ("122".to_string(), vec![(0, 1), (1, 3)])
+        );
+        // an empty match inserts its replacement
+        assert_eq!(
+            replace("abc", vec![m(0, 0, 0, Some("<")), m(0, 3, 3, Some(">"))]),
+            ("<abc>".to_string(), vec![(0, 1), (4, 5)])
+        );
+        // matches without a replacement are dropped
+        assert_eq!(
+            replace("abc", vec![m(0, 0, 1, None), m(1, 1, 2, Some("B"))]),
+            ("aBc".to_string(), vec![(1, 2)])
+        );
+    }
+
+    /// the number and text of each line of `text` that `pattern` matches
+    fn matched_lines(pattern: &str, text: &[u8]) -> Vec<(usize, Vec<u8>)> {
+        let options = SearchOptions::new([pattern]).unwrap();
+        let mut file = File::new(PathBuf::from("test.txt")).unwrap();
+        file.add_matches(text, &options, None).unwrap();
+        file.lines
+            .into_iter()
+            .map(|line| (line.line_num, line.contents))
+            .collect()
+    }
+
+    #[test]
+    fn line_numbers_across_blocks() {
+        // far more than the 64 KiB that the line buffer reads at once
+        let text: String = (1..=20_000)
+            .map(|i| match i {
+                1 | 5_958 | 5_959 | 20_000 => format!("needle {}\n", i),
+                _ => format!("line {}\n", i),
+            })
+            .collect();
+        let found: Vec<usize> = matched_lines("needle", text.as_bytes())
+            .into_iter()
+            .map(|(line_num, _)| line_num)
+            .collect();
+        assert_eq!(found, vec![1, 5_958, 5_959, 20_000]);
+    }
+
+    #[test]
+    fn anchors_match_at_each_line() {
+        let text: &[u8] = b"foo 1\nbar\nfoo 2\nfoo 3\n";
+        let line_nums = |pattern: &str| -> Vec<usize> {
+            matched_lines(pattern, text)
+                .into_iter()
+                .map(|(line_num, _)| line_num)
+                .collect()
+        };
+        assert_eq!(line_nums(r"\Afoo"), vec![1, 3, 4]);
+        assert_eq!(line_nums(r"foo \d\z"), vec![1, 3, 4]);
+        assert_eq!(line_nums("(?-m)^bar$"), vec![2]);
+        assert_eq!(line_nums("^foo"), vec![1, 3, 4]);
+        assert_eq!(line_nums(r"\d$"), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn crlf_lines() {
+        let text: &[u8] = b"foo\r\nbar foo\r\nfoo bar\r\n";
+        assert_eq!(
+            matched_lines("foo$", text),
+            vec![(1, b"foo".to_vec()), (2, b"bar foo".to_vec())]
+        );
+        assert_eq!(matched_lines(r"\Afoo\z", text), vec![(1, b"foo".to_vec())]);
+    }
+
+    #[test]
+    fn lines_longer_than_the_buffer() {
+        let long: String = "a".repeat(200 * 1024);
+        let text: String = format!("start\n{}needle\nneedle\n{}\n", long, long);
+        let found = matched_lines("needle|a{5}$", text.as_bytes());
+        let lens: Vec<(usize, usize)> = found
+            .iter()
+            .map(|(line_num, contents)| (*line_num, contents.len()))
+            .collect();
+        assert_eq!(lens, vec![(2, long.len() + 6), (3, 6), (4, long.len())]);
+    }
+
+    #[test]
+    fn binary_detection() {
+        // "Привет, мир" in cp1251 and "日本語のテキスト" in Shift_JIS
+        let cp1251: &[u8] = b"\xcf\xf0\xe8\xe2\xe5\xf2, \xec\xe8\xf0\r\n";
+        let sjis: &[u8] = b"\x93\xfa\x96\x7b\x8c\xea\x82\xcc\x83\x65\x83\x4c\x83\x58\x83\x67\n";
+        assert!(!is_binary(cp1251));
+        assert!(!is_binary(sjis));
+        assert!(!is_binary(b"\x1b[1mbold\x1b[0m\tand\x0cpage\n"));
+        assert!(is_binary(b"text then a NUL\0"));
+        assert!(is_binary(b"\x7fELF\x02\x01\x01\x03\x02\x3e\x01\x10"));
+    }
+
+    #[test]
+    fn line_limits() {
+        let options = SearchOptions::new(["foo"]).unwrap();
+        assert_eq!(options.line_limit(None), None);
+        assert_eq!(options.line_limit(Some(5)), Some(5));
+        let options = options.max_count(Some(3));
+        assert_eq!(options.line_limit(None), Some(3));
+        assert_eq!(options.line_limit(Some(5)), Some(3));
+        assert_eq!(options.line_limit(Some(2)), Some(2));
+    }
+
+    #[test]
+    fn take_from_the_total() {
+        let mut file = File::new(PathBuf::from("a.txt")).unwrap();
+        let mut left: Option<usize> = Some(5);
+        take(&mut left, &file);
+        assert_eq!(left, Some(5));
+        // a file matched by its name counts once
+        file.name_matches.push(m(0, 0, 1, None));
+        take(&mut left, &file);
+        assert_eq!(left, Some(4));
+        file.lines = matched_file(&["foo"], "foo\nfoo\nfoo\n", None).lines;
+        take(&mut left, &file);
+        assert_eq!(left, Some(1));
+        take(&mut left, &file);
+        assert_eq!(left, Some(0));
+        let mut left: Option<usize> = None;
+        take(&mut left, &file);
+        assert_eq!(left, None);
+    }
+
+    /// `text` searched for `patterns` with `options` added by `with`
+    fn matched_file(
+        patterns: &[&str],
+        text: &str,
+        with: Option<&dyn Fn(SearchOptions) -> SearchOptions>,
+    ) -> File {
+        let mut options = SearchOptions::new(patterns).unwrap();
+        if let Some(with) = with {
+            options = with(options);
+        }
+        let mut file = File::new(PathBuf::from("test.txt")).unwrap();
+        file.add_matches(text.as_bytes(), &options, options.line_limit(None))
+            .unwrap();
+        file
+    }
+
+    #[test]
+    fn max_count_with_all_match() {
+        // `bar` is blocks after the first `foo`
+        let text: String = format!("foo 1\n{}foo 2\nbar\n", "line\n".repeat(30_000));
+        let line_nums =
+            |file: File| -> Vec<usize> { file.lines.iter().map(|line| line.line_num).collect() };
+        let count = |options: SearchOptions| options.max_count(Some(1));
+        assert_eq!(
+            line_nums(matched_file(&["foo", "bar"], &text, Some(&count))),
+            vec![1]
+        );
+        let all = |options: SearchOptions| options.max_count(Some(1)).all_match(true);
+        assert_eq!(
+            line_nums(matched_file(&["foo", "bar"], &text, Some(&all))),
+            vec![1]
+        );
+        assert!(matched_file(&["foo", "bar"], &text, None).matches_all(2));
+        assert!(!matched_file(&["foo", "baz"], &text, None).matches_all(2));
+        // reading on doesn't keep a file missing a pattern
+        let file = matched_file(&["foo", "baz"], &text, Some(&all));
+        assert!(file.is_empty());
+        let mut full = matched_file(&["foo", "bar"], "foo\n", None);
+        let options = SearchOptions::new(["foo", "bar"]).unwrap();
+        assert!(full.is_full(&options, Some(1)));
+        assert!(!full.is_full(&options, Some(2)));
+        assert!(!full.is_full(&options.all_match(true), Some(1)));
+        full.lines.clear();
+        assert!(!full.is_full(&SearchOptions::new(["foo"]).unwrap(), Some(1)));
+    }
+
+    #[test]
+    fn and_skips_blocks_missing_a_pattern() {
+        // the first blocks only have `foo`, the last one has both
+        let text: String = format!("{}foo bar\nbar\n", "foo\n".repeat(40_000));
+        let and = |options: SearchOptions| options.and(true);
+        let file = matched_file(&["foo", "bar"], &text, Some(&and));
+        let lines: Vec<(usize, Vec<u8>)> = file
+            .lines
+            .into_iter()
+            .map(|line| (line.line_num, line.contents))
+            .collect();
+        assert_eq!(lines, vec![(40_001, b"foo bar".to_vec())]);
+    }
+
+    /// how many lines every file in `searched` has
+    fn total_lines(searched: &Searched) -> usize {
+        match searched {
+            Searched::Dir(dirs) => dirs
+                .iter()
+                .flat_map(|dir| &dir.files)
+                .map(|file| file.lines.len())
+                .sum(),
+            Searched::File(file) => file.lines.len(),
+            Searched::Many(all) => all.iter().map(total_lines).sum(),
+        }
+    }
+
+    #[test]
+    fn max_total_across_files() {
+        let dir = TempDir::new().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(dir.path().join(name), "foo\nfoo\n").unwrap();
+        }
+        let options = SearchOptions::new(["foo"]).unwrap();
+        let searched = search(&[dir.path()], &options.max_total(Some(3))).unwrap();
+        assert_eq!(total_lines(&searched), 3);
+        let options = SearchOptions::new(["foo"]).unwrap();
+        let roots = [dir.path().join("a.txt"), dir.path().join("b.txt")];
+        let searched = search(&roots, &options.max_total(Some(3))).unwrap();
+        assert_eq!(total_lines(&searched), 3);
+    }
+
+    #[test]
+    fn max_total_across_archive_entries() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            zip.start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            zip.write_all(b"foo\nfoo\n").unwrap();
+        }
+        zip.finish().unwrap();
+        let options = SearchOptions::new(["foo"]).unwrap().archives(true);
+        assert_eq!(total_lines(&search(&[&path], &options).unwrap()), 6);
+        let searched = search(&[&path], &options.max_total(Some(3))).unwrap();
+        assert_eq!(total_lines(&searched), 3);
+    }
+
+    #[test]
+    fn roots_with_a_trailing_slash() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub").join("a.txt"), "abc").unwrap();
+        let options = SearchOptions::new(["abc"]).unwrap();
+        for root in ["sub/", "sub/./", "sub//"] {
+            match search(&[dir.path().join(root)], &options).unwrap() {
+                Searched::Dir(dirs) => {
+                    assert_eq!(dirs[0].name, "sub", "{}", root);
+                    assert_eq!(dirs[0].files.len(), 1, "{}", root);
+                }
+                _ => panic!("`{}` wasn't searched as a directory", root),
+            }
+        }
+    }
+
+    #[test]
+    fn gzip_is_decoded_in_process() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt.gz");
+        let mut gz = flate2::write::GzEncoder::new(
+            fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        gz.write_all(b"foo\nbar\n").unwrap();
+        gz.finish().unwrap();
+        let options = SearchOptions::new(["bar"]).unwrap().search_zip(true);
+        match search(&[&path], &options).unwrap() {
+            Searched::File(file) => {
+                assert!(file.compressed);
+                assert_eq!(file.lines[0].line_num, 2);
+            }
+            _ => panic!("a file was searched"),
+        }
+    }
+
+    #[test]
+    fn missing_preprocessor_fails_once() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.txt"), "hello").unwrap();
+        fs::write(dir.path().join("b.pdf"), "hello").unwrap();
+        let options = SearchOptions::new(["hello"])
+            .unwrap()
+            .pre(Some(PathBuf::from("gret-missing-preprocessor")));
+        assert!(matches!(
+            search(&[dir.path()], &options),
+            Err(Errors::PreprocessorNotFound { .. })
+        ));
+        // files the globs leave out are read as they are
+        let options = options.pre_globs(["*.nothing"]).unwrap();
+        let (searched, skipped) = search_with_skipped(&[dir.path()], &options).unwrap();
+        assert!(skipped.is_empty());
+        match searched {
+            Searched::Dir(dirs) => assert_eq!(dirs[0].files.len(), 2),
+            _ => panic!("a directory was searched"),
+        }
+    }
+}
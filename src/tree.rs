@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+use crate::searcher::{Directory, File, MatchedLine, Searched};
+
+/// What a row of the tree holds.
+pub enum NodeKind<'a> {
+    Dir(&'a Directory),
+    File(&'a File),
+    Line(&'a File, &'a MatchedLine),
+    /// the leaf under a binary file whose matches are only counted
+    BinaryMatches(&'a File, usize),
+}
+
+/// One row of the tree, `depth` is 0 for a root and `is_last` is true
+/// when no sibling follows this node under the same parent.
+pub struct Node<'a> {
+    pub depth: usize,
+    pub is_last: bool,
+    pub kind: NodeKind<'a>,
+}
+
+enum Frame<'a> {
+    Dir {
+        dirs: &'a [Directory],
+        dir: &'a Directory,
+        depth: usize,
+        next_child: usize,
+        next_file: usize,
+    },
+    File {
+        file: &'a File,
+        depth: usize,
+        next_line: usize,
+    },
+}
+
+/// Depth first iterator over the rows of a [`Searched`], every output
+/// format walks the tree with this so row indexes agree between them.
+/// With `just_files` only files are given, all at depth 0.
+pub struct Nodes<'a> {
+    roots: std::vec::IntoIter<&'a Searched>,
+    stack: Vec<Frame<'a>>,
+    just_files: bool,
+}
+
+impl Searched {
+    pub fn nodes(&self, just_files: bool) -> Nodes<'_> {
+        let mut roots: Vec<&Searched> = Vec::new();
+        flatten(self, &mut roots);
+        Nodes {
+            roots: roots.into_iter(),
+            stack: Vec::new(),
+            just_files,
+        }
+    }
+}
+
+fn flatten<'a>(searched: &'a Searched, roots: &mut Vec<&'a Searched>) {
+    match searched {
+        Searched::Many(all) => {
+            for s in all {
+                flatten(s, roots);
+            }
+        }
+        _ => roots.push(searched),
+    }
+}
+
+impl<'a> Nodes<'a> {
+    fn file_node(&mut self, file: &'a File, depth: usize, is_last: bool) -> Node<'a> {
+        if !self.just_files {
+            self.stack.push(Frame::File {
+                file,
+                depth,
+                next_line: 0,
+            });
+        }
+        Node {
+            depth: if self.just_files { 0 } else { depth },
+            is_last,
+            kind: NodeKind::File(file),
+        }
+    }
+}
+
+impl<'a> Iterator for Nodes<'a> {
+    type Item = Node<'a>;
+
+    fn next(&mut self) -> Option<Node<'a>> {
+        loop {
+            let top = match self.stack.last_mut() {
+                Some(top) => top,
+                None => match self.roots.next()? {
+                    Searched::Dir(dirs) => {
+                        let root = dirs.first().unwrap();
+                        self.stack.push(Frame::Dir {
+                            dirs,
+                            dir: root,
+                            depth: 0,
+                            next_child: 0,
+                            next_file: 0,
+                        });
+                        // a root without matches is not shown
+                        if self.just_files || (root.children.is_empty() && root.files.is_empty()) {
+                            continue;
+                        }
+                        return Some(Node {
+                            depth: 0,
+                            is_last: true,
+                            kind: NodeKind::Dir(root),
+                        });
+                    }
+                    Searched::File(file) => return Some(self.file_node(file, 0, true)),
+                    Searched::Many(_) => unreachable!(),
+                },
+            };
+            match top {
+                Frame::Dir {
+                    dirs,
+                    dir,
+                    depth,
+                    next_child,
+                    next_file,
+                } => {
+                    let (dirs, dir, depth) = (*dirs, *dir, *depth);
+                    if *next_child < dir.children.len() {
+                        let child = dirs.get(dir.children[*next_child]).unwrap();
+                        *next_child += 1;
+                        let is_last = *next_child == dir.children.len() && dir.files.is_empty();
+                        self.stack.push(Frame::Dir {
+                            dirs,
+                            dir: child,
+                            depth: depth + 1,
+                            next_child: 0,
+                            next_file: 0,
+                        });
+                        if self.just_files {
+                            continue;
+                        }
+                        return Some(Node {
+                            depth: depth + 1,
+                            is_last,
+                            kind: NodeKind::Dir(child),
+                        });
+                    } else if *next_file < dir.files.len() {
+                        let file = &dir.files[*next_file];
+                        *next_file += 1;
+                        let is_last = *next_file == dir.files.len();
+                        return Some(self.file_node(file, depth + 1, is_last));
+                    }
+                }
+                Frame::File {
+                    file,
+                    depth,
+                    next_line,
+                } => {
+                    if let (Some(count), 0) = (file.binary_matches, *next_line) {
+                        *next_line += 1;
+                        return Some(Node {
+                            depth: *depth + 1,
+                            is_last: true,
+                            kind: NodeKind::BinaryMatches(file, count),
+                        });
+                    }
+                    if let Some(line) = file.lines.get(*next_line) {
+                        *next_line += 1;
+                        return Some(Node {
+                            depth: *depth + 1,
+                            is_last: *next_line == file.lines.len(),
+                            kind: NodeKind::Line(file, line),
+                        });
+                    }
+                }
+            }
+            self.stack.pop();
+        }
+    }
+}
@@ -0,0 +1,349 @@
+// SPDX-License-Identifier: CC-BY-4.0
+
+use crate::encoding::{decode_file, encode_file};
+use crate::errors::Errors;
+use crate::searcher::{File, MatchedLine, Searched};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Write the replacements of every matched line back to its file.
+/// Each file is written to a temporary file next to it that is then
+/// renamed over it, with the permissions of the original. With
+/// `backup_suffix` the original is first copied to its path with the
+/// suffix added, a backup that is already there is never overwritten and
+/// files ending in the suffix are left alone. A file that changed since it
+/// was searched or that is binary is not written, and a file reached again
+/// through a link is only written once.
+///
+/// Afterwards `searched` only holds the written files and the lines
+/// that changed in them, the errors are for the files that weren't written.
+pub fn write_replacements(searched: &mut Searched, backup_suffix: Option<&str>) -> Vec<Errors> {
+    let mut errors: Vec<Errors> = Vec::new();
+    let mut written: HashSet<PathBuf> = HashSet::new();
+    searched.retain_files(
+        &mut |file: &mut File| match write_file(file, backup_suffix, &mut written) {
+            Ok(was_written) => was_written,
+            Err(e) => {
+                errors.push(e);
+                false
+            }
+        },
+    );
+    errors
+}
+
+/// whether the file was written, it isn't when nothing would change
+fn write_file(
+    file: &mut File,
+    backup_suffix: Option<&str>,
+    written: &mut HashSet<PathBuf>,
+) -> Result<bool, Errors> {
+    if !file
+        .lines
+        .iter()
+        .any(|l| l.matches.iter().any(|m| m.replacement.is_some()))
+    {
+        return Ok(false);
+    }
+    // a backup from an earlier run
+    if backup_suffix.is_some_and(|suffix| file.name.ends_with(suffix)) {
+        return Ok(false);
+    }
+    if file.binary == Some(true) {
+        return Err(Errors::BinaryFile {
+            cause: file.path.clone(),
+        });
+    }
+    if file.compressed {
+        return Err(Errors::CompressedFile {
+            cause: file.path.clone(),
+        });
+    }
+    if file.preprocessed {
+        return Err(Errors::PreprocessedFile {
+            cause: file.path.clone(),
+        });
+    }
+    if file.archived.is_some() {
+        return Err(Errors::ArchivedFile {
+            cause: file.path.clone(),
+        });
+    }
+    let changed = || Errors::FileChanged {
+        cause: file.path.clone(),
+    };
+    // write through links so they still point to the file
+    let path: PathBuf = fs::canonicalize(&file.path).map_err(|e| io_error(&file.path, e))?;
+    if written.contains(&path) {
+        return Ok(false);
+    }
+    let metadata = fs::metadata(&path).map_err(|e| io_error(&path, e))?;
+    let stamp = metadata.modified().ok().map(|m| (m, metadata.len()));
+    if stamp.is_none() || stamp != file.read_stamp {
+        return Err(changed());
+    }
+    let raw: Vec<u8> = fs::read(&path).map_err(|e| io_error(&path, e))?;
+    let cant_encode = || Errors::CantEncode {
+        cause: format!(
+            "`{}` as {}",
+            file.path.display(),
+            file.encoding.map_or("UTF-8", |e| e.name())
+        ),
+    };
+    let encode = |text: &[u8]| match file.encoding {
+        Some(encoding) => encode_file(text, encoding, file.bom).ok_or_else(cant_encode),
+        None => Ok(text.to_vec()),
+    };
+    // the lines were matched in the decoded text, it has to turn back
+    // into the same bytes for the rest of the file to be kept as it is
+    let contents: Vec<u8> = match file.encoding {
+        Some(encoding) => decode_file(&raw, encoding, file.bom),
+        None => raw.clone(),
+    };
+    if encode(&contents)? != raw {
+        return Err(cant_encode());
+    }
+
+    let mut replaced: Vec<u8> = Vec::with_capacity(contents.len());
+    let mut changed_lines: Vec<bool> = Vec::with_capacity(file.lines.len());
+    let mut matched = file.lines.iter().peekable();
+    for (i, line) in contents.split_inclusive(|&b| b == b'\n').enumerate() {
+        let matched_line: &MatchedLine = match matched.next_if(|m| m.line_num == i + 1) {
+            Some(m) => m,
+            None => {
+                replaced.extend_from_slice(line);
+                continue;
+            }
+        };
+        let ending = line_ending(line, &matched_line.contents).ok_or_else(changed)?;
+        let new_line: Vec<u8> = matched_line.replaced().contents;
+        changed_lines.push(new_line != matched_line.contents);
+        replaced.extend_from_slice(&new_line);
+        replaced.extend_from_slice(ending);
+    }
+    // the file got shorter than the lines that were matched
+    if matched.next().is_some() {
+        return Err(changed());
+    }
+    if replaced == contents {
+        return Ok(false);
+    }
+
+    let dir: &Path = path.parent().unwrap_or(Path::new("."));
+    let mut temp = NamedTempFile::new_in(dir).map_err(|e| io_error(&path, e))?;
+    temp.write_all(&encode(&replaced)?)
+        .map_err(|e| io_error(&path, e))?;
+    temp.as_file()
+        .set_permissions(metadata.permissions())
+        .map_err(|e| io_error(&path, e))?;
+    if let Some(suffix) = backup_suffix {
+        let mut backup = path.clone().into_os_string();
+        backup.push(suffix);
+        write_backup(&path, Path::new(&backup), &metadata)?;
+    }
+    temp.persist(&path).map_err(|e| io_error(&path, e.error))?;
+    written.insert(path);
+
+    let mut changed_lines = changed_lines.into_iter();
+    file.lines.retain(|_| changed_lines.next().unwrap_or(false));
+    Ok(true)
+}
+
+/// copy `path` to `backup` unless something is already there
+fn write_backup(path: &Path, backup: &Path, metadata: &fs::Metadata) -> Result<(), Errors> {
+    let mut out: fs::File = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(backup)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => Errors::BackupExists {
+                cause: backup.to_path_buf(),
+            },
+            _ => io_error(backup, e),
+        })?;
+    let mut original: fs::File = fs::File::open(path).map_err(|e| io_error(path, e))?;
+    io::copy(&mut original, &mut out).map_err(|e| io_error(backup, e))?;
+    out.set_permissions(metadata.permissions())
+        .map_err(|e| io_error(backup, e))
+}
+
+/// what follows `contents` in `line`, `None` if the line isn't `contents`
+/// followed by a line ending
+fn line_ending<'a>(line: &'a [u8], contents: &[u8]) -> Option<&'a [u8]> {
+    let ending = line.strip_prefix(contents)?;
+    matches!(ending, b"" | b"\n" | b"\r\n").then_some(ending)
+}
+
+fn io_error(path: &Path, e: io::Error) -> Errors {
+    Errors::IOError {
+        cause: format!("failed to write `{}`: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::Encoding;
+    use crate::searcher::{search, BinaryMode, SearchOptions};
+    use tempfile::TempDir;
+
+    /// the file at `path` searched for `pattern` to be replaced with `replacement`
+    fn searched(path: &Path, pattern: &str, replacement: &str) -> File {
+        let options = SearchOptions::new([pattern])
+            .unwrap()
+            .replace(Some(replacement.to_string()));
+        match search(&[path], &options).unwrap() {
+            Searched::File(file) => file,
+            _ => panic!("`{}` wasn't searched as a file", path.display()),
+        }
+    }
+
+    fn write(file: &mut File, backup_suffix: Option<&str>) -> Result<bool, Errors> {
+        write_file(file, backup_suffix, &mut HashSet::new())
+    }
+
+    #[test]
+    fn line_endings() {
+        assert_eq!(line_ending(b"foo\n", b"foo"), Some(&b"\n"[..]));
+        assert_eq!(line_ending(b"foo\r\n", b"foo"), Some(&b"\r\n"[..]));
+        assert_eq!(line_ending(b"foo", b"foo"), Some(&b""[..]));
+        assert_eq!(line_ending(b"foo\n", b"fo"), None);
+        assert_eq!(line_ending(b"fob\n", b"foo"), None);
+    }
+
+    #[test]
+    fn replaces_lines() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "foo one\nbar\nfoo two").unwrap();
+        let mut file = searched(&path, "foo", "baz");
+        assert!(write(&mut file, None).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "baz one\nbar\nbaz two");
+        assert_eq!(file.lines.len(), 2);
+    }
+
+    #[test]
+    fn keeps_crlf() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "foo\r\nbar\r\nfoo\r\n").unwrap();
+        let mut file = searched(&path, "foo", "baz");
+        assert!(write(&mut file, None).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "baz\r\nbar\r\nbaz\r\n");
+    }
+
+    #[test]
+    fn unchanged_file_isnt_written() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "foo\n").unwrap();
+        let mut file = searched(&path, "foo", "foo");
+        assert!(!write(&mut file, None).unwrap());
+    }
+
+    #[test]
+    fn refuses_changed_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "foo\n").unwrap();
+        let mut file = searched(&path, "foo", "baz");
+        fs::write(&path, "foo\nmore\n").unwrap();
+        assert!(matches!(
+            write(&mut file, None),
+            Err(Errors::FileChanged { .. })
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\nmore\n");
+    }
+
+    #[test]
+    fn refuses_shorter_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "a\nb\nfoo\n").unwrap();
+        let mut file = searched(&path, "foo", "baz");
+        fs::write(&path, "foo\n").unwrap();
+        // the stamp can't tell, so the missing line has to
+        let metadata = fs::metadata(&path).unwrap();
+        file.read_stamp = Some((metadata.modified().unwrap(), metadata.len()));
+        assert!(matches!(
+            write(&mut file, None),
+            Err(Errors::FileChanged { .. })
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\n");
+    }
+
+    #[test]
+    fn utf16_with_bom() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        let utf16le: Encoding = Encoding::for_label("utf-16le").unwrap();
+        let utf16 = |text: &str| encode_file(text.as_bytes(), utf16le, true).unwrap();
+        fs::write(&path, utf16("héllo\r\nwörld\n")).unwrap();
+        let mut file = searched(&path, "ö", "o");
+        assert_eq!(file.encoding, Some(utf16le));
+        assert!(write(&mut file, None).unwrap());
+        assert_eq!(fs::read(&path).unwrap(), utf16("héllo\r\nworld\n"));
+    }
+
+    #[test]
+    fn backup_suffix() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "foo\n").unwrap();
+        let mut file = searched(&path, "foo", "baz");
+        assert!(write(&mut file, Some(".bak")).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "baz\n");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt.bak")).unwrap(),
+            "foo\n"
+        );
+    }
+    #[test]
+    fn keeps_an_existing_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        let backup = dir.path().join("a.txt.bak");
+        fs::write(&path, "foo\n").unwrap();
+        fs::write(&backup, "older\n").unwrap();
+        let mut file = searched(&path, "foo", "baz");
+        assert!(matches!(
+            write(&mut file, Some(".bak")),
+            Err(Errors::BackupExists { .. })
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\n");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "older\n");
+    }
+
+    #[test]
+    fn skips_backups() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.txt.bak");
+        fs::write(&path, "foo\n").unwrap();
+        let mut file = searched(&path, "foo", "baz");
+        assert!(!write(&mut file, Some(".bak")).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\n");
+    }
+
+    #[test]
+    fn refuses_binary_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("a.bin");
+        fs::write(&path, "foo\0\n").unwrap();
+        let options = SearchOptions::new(["foo"])
+            .unwrap()
+            .replace(Some("baz".to_string()))
+            .binary(BinaryMode::Text);
+        let mut file = match search(&[&path], &options).unwrap() {
+            Searched::File(file) => file,
+            _ => panic!("`{}` wasn't searched as a file", path.display()),
+        };
+        assert!(matches!(
+            write(&mut file, None),
+            Err(Errors::BinaryFile { .. })
+        ));
+        assert_eq!(fs::read(&path).unwrap(), b"foo\0\n");
+    }
+}